@@ -1,6 +1,58 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
 use std::process::Command;
 
+fn content_hash(data: &[u8]) -> u64 {
+    // FNV-1a
+    data.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn process_dir(root: &Path, dir: &Path, etags: &mut Vec<(String, String)>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            process_dir(root, &path, etags);
+            continue;
+        }
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") | Some("br") => continue,
+            _ => {}
+        }
+        let contents = fs::read(&path).unwrap();
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        let rel_path = path
+            .strip_prefix(root)
+            .unwrap()
+            .components()
+            .map(|c| c.as_os_str().to_str().unwrap())
+            .collect::<Vec<_>>()
+            .join("/");
+        etags.push((rel_path, format!("{:016x}", content_hash(&contents))));
+
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        gz.write_all(&contents).unwrap();
+        fs::write(
+            path.with_file_name(format!("{}.gz", file_name)),
+            gz.finish().unwrap(),
+        )
+        .unwrap();
+
+        let mut br = Vec::new();
+        {
+            let mut writer = brotli::CompressorWriter::new(&mut br, 4096, 11, 22);
+            writer.write_all(&contents).unwrap();
+        }
+        fs::write(path.with_file_name(format!("{}.br", file_name)), br).unwrap();
+    }
+}
+
 fn main() {
+    if std::env::var_os("CARGO_FEATURE_WEB_UI").is_none() {
+        return;
+    }
     let profile = std::env::var("PROFILE").unwrap();
     let trunk_args = if profile == "release" {
         vec!["build", "--release"]
@@ -13,6 +65,20 @@ fn main() {
         .status()
         .unwrap();
     assert!(status.success());
+    let dist = Path::new("./lumi-web/dist");
+    let mut etags = Vec::new();
+    process_dir(dist, dist, &mut etags);
+    etags.sort();
+    let table: String = etags
+        .iter()
+        .map(|(path, etag)| format!("    ({:?}, {:?}),\n", path, etag))
+        .collect();
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    fs::write(
+        Path::new(&out_dir).join("etags.rs"),
+        format!("pub static ETAGS: &[(&str, &str)] = &[\n{}];\n", table),
+    )
+    .unwrap();
     println!("cargo:rerun-if-changed=lumi-web/src");
     println!("cargo:rerun-if-changed=lumi-web/static/style.css");
     println!("cargo:rerun-if-changed=lumi-web/index.html");
@@ -0,0 +1,246 @@
+use lumi_server_defs::{
+    AccountOverview, AccountSummary, ApiError, BudgetOptions, BudgetReport, ChangeLog, Changes,
+    ChangesOptions, Commodity, CommodityDetails, CompleteOptions, Completion, Document,
+    DocumentFileOptions, DocumentOptions, FilterOptions, Health, JournalItem, Position,
+    RefreshTime, ServerOptions, TrieOptions, TrieTable, UploadOptions, UploadedDocument, View,
+    ViewOptions,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::HashMap;
+
+pub type Journal = Vec<JournalItem<String, lumi::Transaction>>;
+
+async fn read_json<D: DeserializeOwned>(resp: reqwest::Response) -> anyhow::Result<D> {
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp.json().await?);
+    }
+    match resp.json::<ApiError>().await {
+        Ok(error) => Err(error.into()),
+        Err(_) => Err(anyhow::anyhow!("HTTP {}", status)),
+    }
+}
+
+// Escapes a name, e.g. an account or commodity, as one path segment, so that
+// characters like `/`, `?` or `#` stay part of it.
+fn path_segment(name: &str) -> String {
+    let mut url = reqwest::Url::parse("http://localhost/").unwrap();
+    url.path_segments_mut().unwrap().push(name);
+    url.path()[1..].to_string()
+}
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl Client {
+    /// `base_url` is the address the server listens on, e.g. `http://127.0.0.1:3000`.
+    pub fn new(base_url: &str) -> Client {
+        Client::with_http_client(reqwest::Client::new(), base_url)
+    }
+
+    pub fn with_http_client(http: reqwest::Client, base_url: &str) -> Client {
+        Client {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn url(&self, rel_url: &str) -> String {
+        format!("{}/{}", self.base_url, rel_url)
+    }
+
+    async fn get<D: DeserializeOwned>(&self, rel_url: &str) -> anyhow::Result<D> {
+        read_json(self.http.get(self.url(rel_url)).send().await?).await
+    }
+
+    async fn get_with_query<D, Q>(&self, rel_url: &str, query: &Q) -> anyhow::Result<D>
+    where
+        D: DeserializeOwned,
+        Q: Serialize,
+    {
+        let query = serde_urlencoded::to_string(query)?;
+        self.get(&format!("{}?{}", rel_url, query)).await
+    }
+
+    pub async fn refresh(&self) -> anyhow::Result<RefreshTime> {
+        read_json(self.http.post(self.url("api/refresh")).send().await?).await
+    }
+
+    pub async fn balances(&self) -> anyhow::Result<HashMap<String, Vec<Position>>> {
+        self.get("api/balances").await
+    }
+
+    pub async fn journal(&self, options: &FilterOptions) -> anyhow::Result<(Journal, usize)> {
+        self.get_with_query("api/journal", options).await
+    }
+
+    pub async fn account_journal(
+        &self,
+        account: &str,
+        options: &FilterOptions,
+    ) -> anyhow::Result<(Journal, usize)> {
+        self.get_with_query(&format!("api/account/{}", path_segment(account)), options)
+            .await
+    }
+
+    pub async fn account_info(&self, account: &str) -> anyhow::Result<AccountOverview> {
+        self.get(&format!("api/account_info/{}", path_segment(account)))
+            .await
+    }
+
+    pub async fn accounts(&self) -> anyhow::Result<Vec<AccountSummary>> {
+        self.get("api/accounts").await
+    }
+
+    pub async fn budget(&self, options: &BudgetOptions) -> anyhow::Result<BudgetReport> {
+        self.get_with_query("api/budget", options).await
+    }
+
+    pub async fn trie(
+        &self,
+        root: &str,
+        options: &TrieOptions,
+    ) -> anyhow::Result<TrieTable<String>> {
+        self.get_with_query(&format!("api/trie/{}", path_segment(root)), options)
+            .await
+    }
+
+    pub async fn changes(
+        &self,
+        options: &ChangesOptions,
+    ) -> anyhow::Result<ChangeLog<Changes<lumi::Transaction>>> {
+        self.get_with_query("api/changes", options).await
+    }
+
+    pub async fn complete(&self, options: &CompleteOptions) -> anyhow::Result<Vec<Completion>> {
+        self.get_with_query("api/complete", options).await
+    }
+
+    pub async fn errors(&self) -> anyhow::Result<Vec<lumi::Error>> {
+        self.get("api/errors").await
+    }
+
+    pub async fn commodities(&self) -> anyhow::Result<Vec<Commodity>> {
+        self.get("api/commodities").await
+    }
+
+    pub async fn commodity(&self, name: &str) -> anyhow::Result<CommodityDetails> {
+        self.get(&format!("api/commodity/{}", path_segment(name)))
+            .await
+    }
+
+    pub async fn documents(&self, options: &DocumentOptions) -> anyhow::Result<Vec<Document>> {
+        self.get_with_query("api/documents", options).await
+    }
+
+    /// Returns the contents of a document, with `path` as listed by `documents`.
+    pub async fn document_file(&self, path: &str) -> anyhow::Result<Vec<u8>> {
+        let query = serde_urlencoded::to_string(&DocumentFileOptions {
+            path: path.to_string(),
+        })?;
+        let resp = self
+            .http
+            .get(format!("{}?{}", self.url("api/documents/file"), query))
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            return read_json(resp).await;
+        }
+        Ok(resp.bytes().await?.to_vec())
+    }
+
+    /// Stores a PDF or image as a document of `options.account`, e.g. with
+    /// `content_type` `application/pdf`.
+    pub async fn upload_document(
+        &self,
+        options: &UploadOptions,
+        content_type: &str,
+        data: Vec<u8>,
+    ) -> anyhow::Result<UploadedDocument> {
+        let query = serde_urlencoded::to_string(options)?;
+        read_json(
+            self.http
+                .post(format!("{}?{}", self.url("api/documents"), query))
+                .header(reqwest::header::CONTENT_TYPE, content_type)
+                .body(data)
+                .send()
+                .await?,
+        )
+        .await
+    }
+
+    pub async fn views(&self) -> anyhow::Result<Vec<View>> {
+        self.get("api/views").await
+    }
+
+    /// Returns all views after saving.
+    pub async fn save_view(&self, view: &View) -> anyhow::Result<Vec<View>> {
+        read_json(
+            self.http
+                .post(self.url("api/views"))
+                .json(view)
+                .send()
+                .await?,
+        )
+        .await
+    }
+
+    /// Returns the remaining views.
+    pub async fn delete_view(&self, name: &str) -> anyhow::Result<Vec<View>> {
+        let query = serde_urlencoded::to_string(&ViewOptions {
+            name: name.to_string(),
+        })?;
+        read_json(
+            self.http
+                .delete(format!("{}?{}", self.url("api/views"), query))
+                .send()
+                .await?,
+        )
+        .await
+    }
+
+    pub async fn options(&self) -> anyhow::Result<ServerOptions> {
+        self.get("api/options").await
+    }
+
+    pub async fn openapi(&self) -> anyhow::Result<serde_json::Value> {
+        self.get("api/openapi.json").await
+    }
+
+    /// Returns the health report even when the server answers 503.
+    pub async fn health(&self) -> anyhow::Result<Health> {
+        Ok(self
+            .http
+            .get(self.url("healthz"))
+            .send()
+            .await?
+            .json()
+            .await?)
+    }
+
+    pub async fn metrics(&self) -> anyhow::Result<String> {
+        Ok(self
+            .http
+            .get(self.url("metrics"))
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_path_segments() {
+        assert_eq!(path_segment("Assets:Cash"), "Assets:Cash");
+        assert_eq!(path_segment("S&P 500/ETF?#1"), "S&P%20500%2FETF%3F%231");
+    }
+}
@@ -1,9 +1,33 @@
-use std::{collections::HashMap, fmt::Debug, hash::Hash};
+use std::{collections::HashMap, fmt, fmt::Debug, hash::Hash};
 
-use lumi::{Currency, UnitCost};
+use chrono::NaiveDate;
+use lumi::{Amount, Currency, UnitCost};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
+// `Assets:Cash` is a sub-account of itself and of `Assets`, but
+// `Assets:CashBack` is not one of `Assets:Cash`.
+pub fn is_sub_account(account: &str, parent: &str) -> bool {
+    account.starts_with(parent)
+        && (account.len() == parent.len() || account[parent.len()..].starts_with(':'))
+}
+
+// The file name of an account or commodity in a static export. It has to be
+// valid on every file system and stay inside its directory, so bytes other
+// than ASCII letters, digits and `-` become `_` and two hex digits:
+// `Assets:Cash` is `Assets_3ACash`.
+pub fn export_name(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' {
+            result.push(byte as char);
+        } else {
+            result.push_str(&format!("_{:02X}", byte));
+        }
+    }
+    result
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct Position {
     pub currency: Currency,
@@ -20,6 +44,14 @@ pub struct FilterOptions {
     pub old_first: Option<bool>,
     pub account: Option<String>,
     pub time: Option<String>,
+    pub show_lots: Option<bool>,
+    pub changed_since: Option<u64>,
+    pub flags: Option<String>,
+    pub exclude_flags: Option<String>,
+    pub currency: Option<String>,
+    pub min_amount: Option<Decimal>,
+    pub max_amount: Option<Decimal>,
+    pub account_postings: Option<bool>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
@@ -52,9 +84,271 @@ pub struct JournalItem<C: Hash + Eq, T> {
     pub txn: T,
     pub balance: HashMap<C, Decimal>,
     pub changes: HashMap<C, Decimal>,
+    #[serde(default)]
+    pub lots: Vec<Position>,
+    #[serde(default)]
+    pub book_value: HashMap<C, Decimal>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RefreshTime {
     pub timestamp: i64,
+    #[serde(default)]
+    pub generation: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct ChangesOptions {
+    pub since: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModifiedTxn<T> {
+    pub before: T,
+    pub after: T,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct BalanceChange {
+    pub account: String,
+    pub before: HashMap<String, Decimal>,
+    pub after: HashMap<String, Decimal>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Changes<T> {
+    pub generation: u64,
+    pub loaded_at: i64,
+    pub added: Vec<T>,
+    pub removed: Vec<T>,
+    pub modified: Vec<ModifiedTxn<T>>,
+    pub accounts: Vec<BalanceChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeLog<T> {
+    pub generation: u64,
+    pub truncated: bool,
+    pub changes: Vec<T>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum CompletionKind {
+    Account,
+    Payee,
+    Narration,
+    Tag,
+    Link,
+    Currency,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct CompleteOptions {
+    pub kind: CompletionKind,
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Completion {
+    pub value: String,
+    pub count: usize,
+    #[serde(default)]
+    pub accounts: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct AccountBalance {
+    pub name: String,
+    pub balance: HashMap<String, Decimal>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceAssertion {
+    pub date: NaiveDate,
+    pub amounts: Vec<Amount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct AccountOverview {
+    pub name: String,
+    pub open: Option<NaiveDate>,
+    pub close: Option<NaiveDate>,
+    pub currencies: Vec<Currency>,
+    pub meta: Vec<(String, String)>,
+    pub balance: HashMap<String, Decimal>,
+    pub children: Vec<AccountBalance>,
+    pub last_txn: Option<NaiveDate>,
+    pub last_balance: Option<BalanceAssertion>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountSummary {
+    pub name: String,
+    pub open: NaiveDate,
+    pub close: Option<NaiveDate>,
+    pub postings: usize,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct BudgetOptions {
+    pub time: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BudgetRow {
+    pub account: String,
+    pub currency: String,
+    pub budgeted: Decimal,
+    pub actual: Decimal,
+    pub remaining: Decimal,
+    pub percent_used: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BudgetReport {
+    pub period: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub previous: String,
+    pub next: String,
+    pub rows: Vec<BudgetRow>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Health {
+    pub ok: bool,
+    pub last_reload: i64,
+    pub errors: usize,
+}
+
+// A saved page: the path of a route plus its query string, e.g.
+// `/journal` and `flags=!&currency=USD`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct View {
+    pub name: String,
+    pub path: String,
+    #[serde(default)]
+    pub query: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViewOptions {
+    pub name: String,
+}
+
+// A file referenced by a `document` entry or by `document` metadata of a
+// transaction or posting. `path` is as written in the ledger.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Document {
+    pub date: NaiveDate,
+    pub path: String,
+    pub accounts: Vec<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(default)]
+pub struct DocumentOptions {
+    pub account: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentFileOptions {
+    pub path: String,
+}
+
+// The file itself is the request body. With `src_file` and `src_line`, the
+// source of the first posting of a transaction on `date`, the file becomes
+// `document` metadata of that transaction; otherwise a `document` entry of
+// `account`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadOptions {
+    pub account: String,
+    pub date: NaiveDate,
+    pub filename: String,
+    #[serde(default)]
+    pub src_file: Option<String>,
+    #[serde(default)]
+    pub src_line: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadedDocument {
+    pub document: Document,
+    // the reload that picked up the new `document` entry
+    pub refresh: RefreshTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Price {
+    pub date: NaiveDate,
+    pub number: Decimal,
+    pub currency: String,
+}
+
+// A currency or security. `units` is the total held in Assets and
+// Liabilities, `precision` the most decimal places used in the ledger.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Commodity {
+    pub name: String,
+    pub meta: Vec<(String, String)>,
+    pub precision: u32,
+    pub units: Decimal,
+    pub latest_price: Option<Price>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommodityHolding {
+    pub account: String,
+    pub units: Decimal,
+    pub book_value: HashMap<String, Decimal>,
+    // at the latest price, empty without one
+    pub market_value: HashMap<String, Decimal>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommodityDetails {
+    pub commodity: Commodity,
+    // oldest first
+    pub prices: Vec<Price>,
+    pub holdings: Vec<CommodityHolding>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ServerOptions {
+    pub read_only: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiError {
+    pub code: u16,
+    pub message: String,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_names_are_plain_file_names() {
+        assert_eq!(export_name("Assets:Cash"), "Assets_3ACash");
+        assert_eq!(export_name(".."), "_2E_2E");
+        assert_eq!(export_name("a/b\\c"), "a_2Fb_5Cc");
+        assert_eq!(export_name("A_B"), "A_5FB");
+        assert_eq!(export_name("Café"), "Caf_C3_A9");
+    }
 }
@@ -1,7 +1,13 @@
-use lumi_server_defs::{FilterOptions, JournalItem, Position, RefreshTime, TrieOptions, TrieTable};
+use lumi_server_defs::{
+    export_name, AccountOverview, AccountSummary, ApiError, BudgetOptions, BudgetReport, ChangeLog,
+    Changes, ChangesOptions, CommodityDetails, Document, DocumentFileOptions, DocumentOptions,
+    FilterOptions, JournalItem, Position, RefreshTime, ServerOptions, TrieOptions, TrieTable,
+    UploadOptions, UploadedDocument, View, ViewOptions,
+};
+use serde::Serialize;
 use std::{collections::HashMap, rc::Rc, string::ToString};
 use yew::{Component, Context};
-use yew_router::history::{BrowserHistory, History};
+use yew_router::history::{AnyHistory, BrowserHistory, HashHistory, History};
 
 pub enum FetchState<T> {
     NotStarted,
@@ -10,11 +16,96 @@ pub enum FetchState<T> {
     Failed(anyhow::Error),
 }
 
-async fn fetch_json_content<D>(url: String) -> anyhow::Result<D>
+async fn fetch_json_content<D, B>(
+    method: reqwest::Method,
+    url: String,
+    body: Option<B>,
+) -> anyhow::Result<D>
 where
     D: for<'de> serde::de::Deserialize<'de>,
+    B: Serialize,
 {
-    Ok(reqwest::get(url).await?.json::<D>().await?)
+    let client = reqwest::Client::new();
+    let mut request = client.request(method, url);
+    if let Some(body) = &body {
+        request = request.json(body);
+    }
+    read_response(request.send().await?).await
+}
+
+async fn read_response<D>(resp: reqwest::Response) -> anyhow::Result<D>
+where
+    D: for<'de> serde::de::Deserialize<'de>,
+{
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp.json::<D>().await?);
+    }
+    match resp.json::<ApiError>().await {
+        Ok(error) => Err(anyhow::Error::new(error)),
+        Err(_) => Err(anyhow::anyhow!("HTTP {}", status)),
+    }
+}
+
+pub fn is_static() -> bool {
+    web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| {
+            document
+                .query_selector("meta[name=lumi-static]")
+                .ok()
+                .flatten()
+        })
+        .is_some()
+}
+
+// A static export only holds the default options of each endpoint, and
+// every page of the journals.
+fn static_url(rel_url: &str) -> anyhow::Result<String> {
+    let (path, query) = rel_url.split_once('?').unwrap_or((rel_url, ""));
+    let path = path.trim_end_matches('/');
+    // Account and commodity names are file names in the export.
+    let mut segments = path.splitn(3, '/');
+    let path = match (segments.next(), segments.next(), segments.next()) {
+        (Some(api), Some(kind), Some(name)) => format!("{}/{}/{}", api, kind, export_name(name)),
+        _ => path.to_string(),
+    };
+    if path == "api/journal" || path.starts_with("api/account/") {
+        let mut options: FilterOptions = serde_urlencoded::from_str(query)?;
+        let page = options.page.take().unwrap_or(1);
+        if options != FilterOptions::default() {
+            anyhow::bail!("Filters are not available in a static export");
+        }
+        Ok(format!("{}/page-{}.json", path, page))
+    } else if query.is_empty() {
+        Ok(format!("{}.json", path))
+    } else {
+        anyhow::bail!("{} is not available in a static export", rel_url)
+    }
+}
+
+// The server answers at the root of its origin, while a static export is
+// resolved against its index page, wherever it is hosted.
+fn absolute_url(rel_url: &str) -> anyhow::Result<String> {
+    let base = if is_static() {
+        web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.base_uri().ok().flatten())
+            .ok_or_else(|| anyhow::anyhow!("Cannot find the document URL"))?
+    } else {
+        let location = BrowserHistory::new().location();
+        format!("{}//{}/", location.protocol(), location.host())
+    };
+    Ok(reqwest::Url::parse(&base)?.join(rel_url)?.to_string())
+}
+
+// The history of the router that `App` renders.
+pub fn history() -> AnyHistory {
+    if is_static() {
+        AnyHistory::Hash(HashHistory::new())
+    } else {
+        AnyHistory::Browser(BrowserHistory::new())
+    }
 }
 
 fn fetch<C, F, D, M>(ctx: &Context<C>, rel_url: &str, callback: F)
@@ -24,16 +115,43 @@ where
     M: Into<C::Message>,
     D: for<'de> serde::de::Deserialize<'de>,
 {
-    let location = BrowserHistory::new().location();
+    send(ctx, reqwest::Method::GET, rel_url, callback);
+}
+
+fn send<C, F, D, M>(ctx: &Context<C>, method: reqwest::Method, rel_url: &str, callback: F)
+where
+    F: Fn(anyhow::Result<D>) -> M + 'static,
+    C: Component,
+    M: Into<C::Message>,
+    D: for<'de> serde::de::Deserialize<'de>,
+{
+    send_json(ctx, method, rel_url, None::<()>, callback);
+}
+
+fn send_json<C, F, D, M, B>(
+    ctx: &Context<C>,
+    method: reqwest::Method,
+    rel_url: &str,
+    body: Option<B>,
+    callback: F,
+) where
+    F: Fn(anyhow::Result<D>) -> M + 'static,
+    C: Component,
+    M: Into<C::Message>,
+    D: for<'de> serde::de::Deserialize<'de>,
+    B: Serialize + 'static,
+{
     let link = ctx.link();
-    let url = format!(
-        "{}//{}/{}",
-        location.protocol(),
-        location.host(),
-        rel_url.to_string()
-    );
+    let url = if is_static() {
+        static_url(rel_url).and_then(|rel_url| absolute_url(&rel_url))
+    } else {
+        absolute_url(rel_url)
+    };
     link.send_future(async move {
-        let result = fetch_json_content(url).await;
+        let result = match url {
+            Ok(url) => fetch_json_content(method, url, body).await,
+            Err(e) => Err(e),
+        };
         callback(result)
     });
 }
@@ -44,8 +162,9 @@ where
     F: Fn(anyhow::Result<i64>) -> M + 'static,
     M: Into<C::Message>,
 {
-    fetch(
+    send(
         ctx,
+        reqwest::Method::POST,
         "api/refresh",
         move |resp: anyhow::Result<RefreshTime>| {
             callback(resp.map(|refresh_time| refresh_time.timestamp))
@@ -103,3 +222,154 @@ pub fn get_account_journal<C, F, M>(
     };
     fetch(ctx, &rel_url, callback);
 }
+
+pub fn get_account_info<C, F, M>(account: &str, ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<AccountOverview>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    let rel_url = format!("api/account_info/{}", account);
+    fetch(ctx, &rel_url, callback);
+}
+
+pub fn get_accounts<C, F, M>(ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<Vec<AccountSummary>>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    fetch(ctx, "api/accounts", callback);
+}
+
+pub fn get_budget<C, F, M>(options: &BudgetOptions, ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<BudgetReport>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    let query = serde_urlencoded::to_string(&options).unwrap();
+    let rel_url = format!("api/budget?{}", query);
+    fetch(ctx, &rel_url, callback);
+}
+
+pub fn get_options<C, F, M>(ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<ServerOptions>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    fetch(ctx, "api/options", callback);
+}
+
+pub type ChangeList = ChangeLog<Changes<Rc<lumi::Transaction>>>;
+pub fn get_changes<C, F, M>(options: &ChangesOptions, ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<ChangeList>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    let query = serde_urlencoded::to_string(&options).unwrap();
+    let rel_url = format!("api/changes?{}", query);
+    fetch(ctx, &rel_url, callback);
+}
+
+pub fn get_views<C, F, M>(ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<Vec<View>>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    fetch(ctx, "api/views", callback);
+}
+
+pub fn save_view<C, F, M>(view: View, ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<Vec<View>>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    send_json(
+        ctx,
+        reqwest::Method::POST,
+        "api/views",
+        Some(view),
+        callback,
+    );
+}
+
+pub fn delete_view<C, F, M>(name: &str, ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<Vec<View>>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    let query = serde_urlencoded::to_string(&ViewOptions {
+        name: name.to_string(),
+    })
+    .unwrap();
+    let rel_url = format!("api/views?{}", query);
+    send(ctx, reqwest::Method::DELETE, &rel_url, callback);
+}
+
+pub fn get_commodity<C, F, M>(name: &str, ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<CommodityDetails>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    let rel_url = format!("api/commodity/{}", name);
+    fetch(ctx, &rel_url, callback);
+}
+
+pub fn get_documents<C, F, M>(options: &DocumentOptions, ctx: &Context<C>, callback: F)
+where
+    C: Component,
+    F: Fn(anyhow::Result<Vec<Document>>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    let query = serde_urlencoded::to_string(&options).unwrap();
+    let rel_url = format!("api/documents?{}", query);
+    fetch(ctx, &rel_url, callback);
+}
+
+// For links, the browser fetches the file itself.
+pub fn document_url(path: &str) -> String {
+    let query = serde_urlencoded::to_string(&DocumentFileOptions {
+        path: path.to_string(),
+    })
+    .unwrap();
+    format!("/api/documents/file?{}", query)
+}
+
+// Sends the file as the request body, with the type the browser detected.
+pub fn upload_document<C, F, M>(
+    options: &UploadOptions,
+    file: web_sys::File,
+    ctx: &Context<C>,
+    callback: F,
+) where
+    C: Component,
+    F: Fn(anyhow::Result<UploadedDocument>) -> M + 'static,
+    M: Into<C::Message>,
+{
+    let query = serde_urlencoded::to_string(&options).unwrap();
+    let url = absolute_url(&format!("api/documents?{}", query));
+    ctx.link().send_future(async move {
+        let result = async {
+            let url = url?;
+            let buffer = wasm_bindgen_futures::JsFuture::from(file.array_buffer())
+                .await
+                .map_err(|_| anyhow::anyhow!("Cannot read {}", file.name()))?;
+            let resp = reqwest::Client::new()
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, file.type_())
+                .body(js_sys::Uint8Array::new(&buffer).to_vec())
+                .send()
+                .await?;
+            read_response(resp).await
+        }
+        .await;
+        callback(result)
+    });
+}
@@ -1,23 +1,35 @@
+use crate::api;
 use crate::components::{
-    ErrorTable, HoldingTable, JournalTable, RefreshButton, Sidebar, TrieTable,
+    AccountHeader, BudgetTable, ChangesBanner, CommodityTable, DocumentList, ErrorTable,
+    HoldingTable, JournalTable, LanguageSelect, OptionsProvider, RefreshButton, Shortcuts, Sidebar,
+    TrieTable,
 };
+use crate::i18n;
 use crate::route::Route;
+use lumi_server_defs::ServerOptions;
 use std::rc::Rc;
 use yew::prelude::*;
 use yew_router::prelude::*;
 
 #[function_component(App)]
 pub fn app() -> Html {
-    html! {
-        <BrowserRouter>
+    let content = html! {
+        <OptionsProvider>
+            <Shortcuts />
             <Sidebar />
             <Switch<Route> render={Switch::render(switch)} />
-        </BrowserRouter>
+        </OptionsProvider>
+    };
+    // A static export is one index page, so it routes on the fragment.
+    if api::is_static() {
+        html! { <HashRouter>{content}</HashRouter> }
+    } else {
+        html! { <BrowserRouter>{content}</BrowserRouter> }
     }
 }
 
 fn switch(routes: &Route) -> Html {
-    let qs = BrowserHistory::new().location().search();
+    let qs = api::history().location().search();
     let mut qs_chars = qs.chars();
     qs_chars.next();
     let qs: Rc<String> = Rc::new(String::from(qs_chars.as_str()));
@@ -34,12 +46,13 @@ struct MainContentProps {
 fn main_content(props: &MainContentProps) -> Html {
     let routes = &props.route;
     let title = match routes {
-        Route::Balance | Route::Index => "Balance Sheet",
-        Route::Holdings => "Holdings",
-        Route::Journal => "Journal",
-        Route::Income => "Income",
-        Route::Account { name } => name.as_str(),
-        Route::Errors => "Errors",
+        Route::Balance | Route::Index => i18n::tr("Balance Sheet"),
+        Route::Holdings => i18n::tr("Holdings"),
+        Route::Journal => i18n::tr("Journal"),
+        Route::Income => i18n::tr("Income Statement"),
+        Route::Account { name } | Route::Commodity { name } => name.as_str(),
+        Route::Errors => i18n::tr("Errors"),
+        Route::Budget => i18n::tr("Budget"),
     };
     let timestamp = use_state_eq(|| 0i64);
     let update_timestamp = {
@@ -49,10 +62,20 @@ fn main_content(props: &MainContentProps) -> Html {
             log::info!("Ledger updated: {}", val);
         })
     };
+    let read_only = use_context::<Rc<ServerOptions>>()
+        .map(|options| options.read_only)
+        .unwrap_or(true);
+    let mode_badge = if read_only {
+        html! {<span id="mode">{i18n::tr("read-only")}</span>}
+    } else {
+        html! {}
+    };
     let title_bar = html! {
         <header>
             <span id="title">{title}</span>
-            <RefreshButton callback={update_timestamp} />
+            {mode_badge}
+            <RefreshButton callback={update_timestamp.clone()} />
+            <LanguageSelect />
         </header>
     };
     let qs = &props.query;
@@ -89,7 +112,7 @@ fn main_content(props: &MainContentProps) -> Html {
         }
         Route::Journal => {
             html! {
-                <JournalTable account={""} options={qs.to_string()}/>
+                <JournalTable account={""} options={qs.to_string()} {read_only}/>
             }
         }
         Route::Holdings => {
@@ -98,8 +121,23 @@ fn main_content(props: &MainContentProps) -> Html {
             }
         }
         Route::Account { name } => {
+            // A static export has no document files to link to.
+            let documents = if api::is_static() {
+                html! {}
+            } else {
+                html! {<DocumentList account={name.to_string()}/>}
+            };
             html! {
-                <JournalTable account={name.to_string()} options={qs.to_string()}/>
+                <>
+                    <AccountHeader account={name.to_string()}/>
+                    {documents}
+                    <JournalTable account={name.to_string()} options={qs.to_string()} {read_only}/>
+                </>
+            }
+        }
+        Route::Commodity { name } => {
+            html! {
+                <CommodityTable name={name.to_string()}/>
             }
         }
         Route::Errors => {
@@ -107,13 +145,23 @@ fn main_content(props: &MainContentProps) -> Html {
                 <ErrorTable/>
             }
         }
+        Route::Budget => {
+            html! {
+                <BudgetTable options={qs.to_string()}/>
+            }
+        }
     };
+    // Components that reload the ledger themselves, like uploads, report the
+    // new timestamp through the `Callback<i64>` context.
     html! {
         <div class="right-wrap">
             {title_bar}
             <main>
                 <ContextProvider<i64> context={*timestamp} >
-                    {content}
+                    <ContextProvider<Callback<i64>> context={update_timestamp}>
+                        <ChangesBanner/>
+                        {content}
+                    </ContextProvider<Callback<i64>>>
                 </ContextProvider<i64>>
             </main>
         </div>
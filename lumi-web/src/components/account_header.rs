@@ -0,0 +1,188 @@
+use crate::api::{self, FetchState};
+use crate::components::AccountRef;
+use crate::i18n;
+use crate::route::Route;
+use anyhow::Error;
+use lumi_server_defs::AccountOverview;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use yew::context::ContextHandle;
+use yew::prelude::*;
+use yew_router::components::Link;
+
+#[derive(Properties, Clone, PartialEq, Eq)]
+pub struct Props {
+    pub account: String,
+}
+
+pub enum Msg {
+    GetInfo,
+    GetInfoSuccess(AccountOverview),
+    GetInfoError(Error),
+}
+
+pub struct AccountHeader {
+    fetch_state: FetchState<AccountOverview>,
+    _handle: ContextHandle<i64>,
+}
+
+fn balance_to_html(balance: &HashMap<String, Decimal>) -> Html {
+    let mut entries: Vec<_> = balance.iter().collect();
+    entries.sort_by_key(|(c, _)| *c);
+    entries
+        .into_iter()
+        .map(|(c, n)| html! {<div>{i18n::amount(n, c)}</div>})
+        .collect()
+}
+
+fn breadcrumbs(account: &str) -> Html {
+    type Anchor = Link<Route>;
+    let parts: Vec<_> = account.split(':').collect();
+    let mut crumbs = vec![];
+    for (index, part) in parts.iter().enumerate() {
+        if index > 0 {
+            crumbs.push(html! {<span class="separator">{":"}</span>});
+        }
+        if index + 1 == parts.len() {
+            crumbs.push(html! {<span class="current">{part}</span>});
+        } else {
+            let dest = Route::Account {
+                name: parts[..=index].join(":"),
+            };
+            crumbs.push(html! {<Anchor to={dest} classes={"account"}>{part}</Anchor>});
+        }
+    }
+    html! {<div class="breadcrumbs">{crumbs}</div>}
+}
+
+impl Component for AccountHeader {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let (_, handle) = ctx
+            .link()
+            .context::<i64>(ctx.link().callback(|_| Msg::GetInfo))
+            .expect("context to be set");
+        ctx.link().send_message(Msg::GetInfo);
+        Self {
+            fetch_state: FetchState::NotStarted,
+            _handle: handle,
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>) -> bool {
+        ctx.link().send_message(Msg::GetInfo);
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetInfoError(err) => {
+                self.fetch_state = FetchState::Failed(err);
+                true
+            }
+            Msg::GetInfoSuccess(info) => {
+                self.fetch_state = FetchState::Success(info);
+                true
+            }
+            Msg::GetInfo => {
+                self.fetch_state = FetchState::Fetching;
+                api::get_account_info(&ctx.props().account, ctx, |result| match result {
+                    Ok(info) => Msg::GetInfoSuccess(info),
+                    Err(err) => Msg::GetInfoError(err),
+                });
+                false
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        match self.fetch_state {
+            FetchState::Failed(ref reason) => html! {<p class="error">{reason.to_string()}</p>},
+            FetchState::Fetching => html! {<p>{i18n::tr("loading")}</p>},
+            FetchState::NotStarted => html! {<p>{i18n::tr("not started")}</p>},
+            FetchState::Success(ref info) => {
+                let mut rows = vec![];
+                if let Some(open) = info.open {
+                    let status = match info.close {
+                        Some(close) => format!("{} – {}", i18n::date(&open), i18n::date(&close)),
+                        None => i18n::trf("since {}", &[&i18n::date(&open)]),
+                    };
+                    rows.push(html! {
+                        <tr><th class="left">{i18n::tr("Open")}</th><td class="mono">{status}</td></tr>
+                    });
+                }
+                if !info.currencies.is_empty() {
+                    type Anchor = Link<Route>;
+                    let currencies: Html = info
+                        .currencies
+                        .iter()
+                        .enumerate()
+                        .map(|(index, currency)| {
+                            let dest = Route::Commodity {
+                                name: currency.to_string(),
+                            };
+                            let separator = if index > 0 { ", " } else { "" };
+                            html! {<>{separator}<Anchor to={dest} classes={"commodity"}>{currency.to_string()}</Anchor></>}
+                        })
+                        .collect();
+                    rows.push(html! {
+                        <tr><th class="left">{i18n::tr("Currencies")}</th><td class="mono">{currencies}</td></tr>
+                    });
+                }
+                rows.push(html! {
+                    <tr><th class="left">{i18n::tr("Balance")}</th><td class="mono">{balance_to_html(&info.balance)}</td></tr>
+                });
+                if let Some(date) = info.last_txn {
+                    rows.push(html! {
+                        <tr><th class="left">{i18n::tr("Last Transaction")}</th><td class="mono">{i18n::date(&date)}</td></tr>
+                    });
+                }
+                if let Some(assertion) = &info.last_balance {
+                    let amounts: Html = assertion
+                        .amounts
+                        .iter()
+                        .map(|amount| html! {<div>{i18n::amount(&amount.number, &amount.currency)}</div>})
+                        .collect();
+                    rows.push(html! {
+                        <tr><th class="left">{i18n::tr("Last Balance")}</th><td class="mono">{i18n::date(&assertion.date)}{amounts}</td></tr>
+                    });
+                }
+                for (key, value) in &info.meta {
+                    rows.push(html! {
+                        <tr><th class="left">{key}</th><td class="mono">{value}</td></tr>
+                    });
+                }
+                let children = if info.children.is_empty() {
+                    html! {}
+                } else {
+                    let child_rows: Html = info
+                        .children
+                        .iter()
+                        .map(|child| {
+                            html! {
+                                <tr>
+                                    <td class="left"><AccountRef account={child.name.clone()}/></td>
+                                    <td class="mono right">{balance_to_html(&child.balance)}</td>
+                                </tr>
+                            }
+                        })
+                        .collect();
+                    html! {
+                        <table class="children">{child_rows}</table>
+                    }
+                };
+                html! {
+                    <div class="card account-header">
+                        {breadcrumbs(&ctx.props().account)}
+                        <div class="account-details">
+                            <table class="info">{rows}</table>
+                            {children}
+                        </div>
+                    </div>
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use crate::api::{self, FetchState};
+use crate::i18n;
+use crate::route::Route;
+use anyhow::Error;
+use lumi_server_defs::AccountSummary;
+use std::collections::{BTreeMap, HashSet};
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+use yew_router::components::Link;
+
+#[derive(Default)]
+struct Node {
+    summary: Option<AccountSummary>,
+    children: BTreeMap<String, Node>,
+}
+
+impl Node {
+    fn insert(&mut self, summary: AccountSummary) {
+        let mut node = self;
+        for part in summary.name.split(':') {
+            node = node.children.entry(part.to_string()).or_default();
+        }
+        node.summary = Some(summary);
+    }
+
+    fn matches(&self, full_name: &str, filter: &str) -> bool {
+        full_name.to_lowercase().contains(filter)
+            || self
+                .children
+                .iter()
+                .any(|(name, child)| child.matches(&format!("{}:{}", full_name, name), filter))
+    }
+}
+
+pub enum Msg {
+    GetAccounts,
+    GetAccountsSuccess(Vec<AccountSummary>),
+    GetAccountsError(Error),
+    Toggle(String),
+    Filter(String),
+}
+
+pub struct AccountTree {
+    fetch_state: FetchState<Node>,
+    expanded: HashSet<String>,
+    filter: String,
+}
+
+impl AccountTree {
+    fn view_children(&self, ctx: &Context<Self>, parent: &str, node: &Node) -> Html {
+        let items: Html = node
+            .children
+            .iter()
+            .filter_map(|(name, child)| {
+                let full_name = if parent.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}:{}", parent, name)
+                };
+                if !self.filter.is_empty() && !child.matches(&full_name, &self.filter) {
+                    return None;
+                }
+                Some(self.view_node(ctx, name, full_name, child))
+            })
+            .collect();
+        html! {<ul>{items}</ul>}
+    }
+
+    fn view_node(&self, ctx: &Context<Self>, name: &str, full_name: String, node: &Node) -> Html {
+        type Anchor = Link<Route>;
+        let expanded = !self.filter.is_empty() || self.expanded.contains(&full_name);
+        let toggle = if node.children.is_empty() {
+            html! {<span class="toggle"></span>}
+        } else {
+            let target = full_name.clone();
+            let onclick = ctx.link().callback(move |_| Msg::Toggle(target.clone()));
+            let arrow = if expanded { "▾" } else { "▸" };
+            html! {<span class="toggle" {onclick}>{arrow}</span>}
+        };
+        let closed = node
+            .summary
+            .as_ref()
+            .map_or(false, |summary| summary.close.is_some());
+        let title = node
+            .summary
+            .as_ref()
+            .map(|summary| i18n::trf("{} postings", &[&summary.postings]))
+            .unwrap_or_default();
+        let children = if expanded && !node.children.is_empty() {
+            self.view_children(ctx, &full_name, node)
+        } else {
+            html! {}
+        };
+        html! {
+            <li class={if closed { "closed" } else { "" }}>
+                {toggle}
+                <Anchor to={Route::Account { name: full_name }} classes={"account"}>
+                    <span title={title}>{name}</span>
+                </Anchor>
+                {children}
+            </li>
+        }
+    }
+}
+
+impl Component for AccountTree {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Msg::GetAccounts);
+        Self {
+            fetch_state: FetchState::NotStarted,
+            expanded: HashSet::new(),
+            filter: String::new(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetAccountsError(err) => {
+                self.fetch_state = FetchState::Failed(err);
+                true
+            }
+            Msg::GetAccountsSuccess(accounts) => {
+                let mut root = Node::default();
+                for summary in accounts {
+                    root.insert(summary);
+                }
+                self.fetch_state = FetchState::Success(root);
+                true
+            }
+            Msg::GetAccounts => {
+                self.fetch_state = FetchState::Fetching;
+                api::get_accounts(ctx, |result| match result {
+                    Ok(accounts) => Msg::GetAccountsSuccess(accounts),
+                    Err(err) => Msg::GetAccountsError(err),
+                });
+                false
+            }
+            Msg::Toggle(account) => {
+                if !self.expanded.remove(&account) {
+                    self.expanded.insert(account);
+                }
+                true
+            }
+            Msg::Filter(filter) => {
+                self.filter = filter.to_lowercase();
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let tree = match self.fetch_state {
+            FetchState::Failed(ref reason) => html! {<p class="error">{reason.to_string()}</p>},
+            FetchState::Fetching => html! {<p>{i18n::tr("loading")}</p>},
+            FetchState::NotStarted => html! {<p>{i18n::tr("not started")}</p>},
+            FetchState::Success(ref root) => self.view_children(ctx, "", root),
+        };
+        let oninput = ctx.link().callback(|e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            Msg::Filter(input.value())
+        });
+        html! {
+            <div class="account-tree">
+                <input id="account-filter" type="search" placeholder={i18n::tr("Filter accounts")} {oninput}/>
+                {tree}
+            </div>
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use crate::api::{self, FetchState};
+use crate::components::AccountRef;
+use crate::i18n;
+use crate::route::Route;
+use anyhow::Error;
+use lumi_server_defs::{BudgetOptions, BudgetReport};
+use rust_decimal::prelude::ToPrimitive;
+use yew::context::ContextHandle;
+use yew::prelude::*;
+use yew_router::components::Link;
+
+#[derive(Properties, Clone, PartialEq, Eq)]
+pub struct Props {
+    pub options: String,
+}
+
+pub enum Msg {
+    GetBudget,
+    GetBudgetSuccess(BudgetReport),
+    GetBudgetError(Error),
+}
+
+pub struct BudgetTable {
+    fetch_state: FetchState<BudgetReport>,
+    options: BudgetOptions,
+    _handle: ContextHandle<i64>,
+}
+
+impl Component for BudgetTable {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let (_, handle) = ctx
+            .link()
+            .context::<i64>(ctx.link().callback(|_| Msg::GetBudget))
+            .expect("context to be set");
+        ctx.link().send_message(Msg::GetBudget);
+        let options = serde_urlencoded::from_str(&ctx.props().options).unwrap_or_default();
+        Self {
+            fetch_state: FetchState::NotStarted,
+            options,
+            _handle: handle,
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>) -> bool {
+        self.options = serde_urlencoded::from_str(&ctx.props().options).unwrap_or_default();
+        ctx.link().send_message(Msg::GetBudget);
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetBudgetError(err) => {
+                self.fetch_state = FetchState::Failed(err);
+                true
+            }
+            Msg::GetBudgetSuccess(report) => {
+                self.fetch_state = FetchState::Success(report);
+                true
+            }
+            Msg::GetBudget => {
+                self.fetch_state = FetchState::Fetching;
+                api::get_budget(&self.options, ctx, |result| match result {
+                    Ok(report) => Msg::GetBudgetSuccess(report),
+                    Err(err) => Msg::GetBudgetError(err),
+                });
+                false
+            }
+        }
+    }
+
+    fn view(&self, _ctx: &Context<Self>) -> Html {
+        match self.fetch_state {
+            FetchState::Failed(ref reason) => html! {<p class="error">{reason.to_string()}</p>},
+            FetchState::Fetching => html! {<p>{i18n::tr("loading")}</p>},
+            FetchState::NotStarted => html! {<p>{i18n::tr("not started")}</p>},
+            FetchState::Success(ref report) => {
+                type Anchor = Link<Route, BudgetOptions>;
+                let previous = BudgetOptions {
+                    time: Some(report.previous.clone()),
+                };
+                let next = BudgetOptions {
+                    time: Some(report.next.clone()),
+                };
+                let rows: Html = report
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        let (bar_class, width) = match row.percent_used {
+                            Some(percent) => {
+                                let class = if row.remaining.is_sign_negative() {
+                                    "bar over"
+                                } else {
+                                    "bar"
+                                };
+                                (class, percent.to_f64().unwrap_or(0.0).clamp(0.0, 100.0))
+                            }
+                            None => ("bar", 0.0),
+                        };
+                        let percent = row
+                            .percent_used
+                            .map(|p| format!("{}%", i18n::number(&p)))
+                            .unwrap_or_default();
+                        html! {
+                            <tr>
+                                <td class="left"><AccountRef account={row.account.clone()}/></td>
+                                <td class="mono right">{i18n::amount(&row.budgeted, &row.currency)}</td>
+                                <td class="mono right">{i18n::amount(&row.actual, &row.currency)}</td>
+                                <td class="mono right">{i18n::amount(&row.remaining, &row.currency)}</td>
+                                <td class="progress">
+                                    <div class="progress">
+                                        <div class={bar_class} style={format!("width: {}%", width)}></div>
+                                    </div>
+                                </td>
+                                <td class="mono right">{percent}</td>
+                            </tr>
+                        }
+                    })
+                    .collect();
+                // A static export only has the current period.
+                let buttons = if api::is_static() {
+                    html! {
+                        <div class="buttons">
+                            <a class="button selected">{&report.period}</a>
+                        </div>
+                    }
+                } else {
+                    html! {
+                        <div class="buttons">
+                            <Anchor to={Route::Budget} query={previous} classes="button">{"<"}</Anchor>
+                            <a class="button selected">{&report.period}</a>
+                            <Anchor to={Route::Budget} query={next} classes="button">{">"}</Anchor>
+                        </div>
+                    }
+                };
+                html! {
+                    <>
+                        <div class="txn-table-head">
+                            {buttons}
+                            <span class="period">{i18n::date(&report.start)}{" – "}{i18n::date(&report.end)}</span>
+                        </div>
+                        <div class="card">
+                            <table class="budget">
+                                <tr>
+                                    <th class="left">{i18n::tr("Account")}</th>
+                                    <th class="right amount">{i18n::tr("Budgeted")}</th>
+                                    <th class="right amount">{i18n::tr("Actual")}</th>
+                                    <th class="right amount">{i18n::tr("Remaining")}</th>
+                                    <th class="left">{i18n::tr("Progress")}</th>
+                                    <th class="right">{i18n::tr("Used")}</th>
+                                </tr>
+                                {rows}
+                            </table>
+                        </div>
+                    </>
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use crate::api::{self, ChangeList};
+use crate::i18n;
+use crate::route::Route;
+use anyhow::Error;
+use lumi_server_defs::{ChangesOptions, FilterOptions};
+use yew::context::ContextHandle;
+use yew::prelude::*;
+use yew_router::components::Link;
+
+pub enum Msg {
+    GetChanges,
+    GetChangesSuccess(ChangeList),
+    GetChangesError(Error),
+    Dismiss,
+}
+
+pub struct ChangesBanner {
+    // the generation the user has seen; changes after it are announced
+    seen: Option<u64>,
+    latest: u64,
+    added: usize,
+    _handle: ContextHandle<i64>,
+}
+
+impl Component for ChangesBanner {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let (_, handle) = ctx
+            .link()
+            .context::<i64>(ctx.link().callback(|_| Msg::GetChanges))
+            .expect("context to be set");
+        if !api::is_static() {
+            ctx.link().send_message(Msg::GetChanges);
+        }
+        Self {
+            seen: None,
+            latest: 0,
+            added: 0,
+            _handle: handle,
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetChanges => {
+                let options = ChangesOptions { since: self.seen };
+                api::get_changes(&options, ctx, |result| match result {
+                    Ok(log) => Msg::GetChangesSuccess(log),
+                    Err(err) => Msg::GetChangesError(err),
+                });
+                false
+            }
+            Msg::GetChangesSuccess(log) => {
+                self.latest = log.generation;
+                if self.seen.is_none() {
+                    self.seen = Some(log.generation);
+                }
+                self.added = log.changes.iter().map(|c| c.added.len()).sum();
+                true
+            }
+            Msg::GetChangesError(err) => {
+                log::error!("Cannot get changes: {}", err);
+                false
+            }
+            Msg::Dismiss => {
+                self.seen = Some(self.latest);
+                self.added = 0;
+                true
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        if self.added == 0 {
+            return html! {};
+        }
+        type Anchor = Link<Route, FilterOptions>;
+        let query = FilterOptions {
+            changed_since: self.seen,
+            ..Default::default()
+        };
+        let text = if self.added == 1 {
+            i18n::tr("1 new transaction").to_string()
+        } else {
+            i18n::trf("{} new transactions", &[&self.added])
+        };
+        let onclick = ctx.link().callback(|_| Msg::Dismiss);
+        html! {
+            <div class="changes-banner">
+                <Anchor to={Route::Journal} {query}>{text}</Anchor>
+                <span class="dismiss" {onclick}>{"×"}</span>
+            </div>
+        }
+    }
+}
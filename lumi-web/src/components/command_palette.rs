@@ -0,0 +1,198 @@
+use crate::api;
+use crate::i18n;
+use crate::route::{self, Route};
+use anyhow::Error;
+use lumi_server_defs::AccountSummary;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+use yew_router::history::History;
+
+const MAX_ITEMS: usize = 12;
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct Props {
+    pub onclose: Callback<()>,
+}
+
+pub enum Msg {
+    GetAccountsSuccess(Vec<AccountSummary>),
+    GetAccountsError(Error),
+    Query(String),
+    Move(isize),
+    Go(usize),
+}
+
+pub struct CommandPalette {
+    accounts: Vec<String>,
+    query: String,
+    selected: usize,
+    input: NodeRef,
+}
+
+// Lower is better, as in /api/complete: prefix, prefix of a segment,
+// substring, then the query's characters in order.
+fn match_rank(candidate: &str, query: &str) -> Option<u8> {
+    if query.is_empty() {
+        return Some(0);
+    }
+    let candidate = candidate.to_lowercase();
+    if candidate.starts_with(query) {
+        return Some(0);
+    }
+    if candidate
+        .match_indices(query)
+        .any(|(i, _)| candidate[..i].ends_with(|c: char| c == ':' || c == ' '))
+    {
+        return Some(1);
+    }
+    if candidate.contains(query) {
+        return Some(2);
+    }
+    let mut chars = candidate.chars();
+    if query.chars().all(|q| chars.any(|c| c == q)) {
+        return Some(3);
+    }
+    None
+}
+
+impl CommandPalette {
+    // Pages first, then accounts, each ordered by how well they match.
+    fn items(&self) -> Vec<(Route, String)> {
+        let query = self.query.trim().to_lowercase();
+        let pages = route::pages()
+            .into_iter()
+            .map(|(route, title)| (route, i18n::tr(title).to_string()));
+        let accounts = self.accounts.iter().map(|name| {
+            let route = Route::Account { name: name.clone() };
+            (route, name.clone())
+        });
+        let mut items: Vec<_> = pages
+            .chain(accounts)
+            .enumerate()
+            .filter_map(|(order, (route, title))| {
+                match_rank(&title, &query).map(|rank| (rank, order, route, title))
+            })
+            .collect();
+        items.sort_by_key(|(rank, order, _, _)| (*rank, *order));
+        items
+            .into_iter()
+            .take(MAX_ITEMS)
+            .map(|(_, _, route, title)| (route, title))
+            .collect()
+    }
+}
+
+impl Component for CommandPalette {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        api::get_accounts(ctx, |result| match result {
+            Ok(accounts) => Msg::GetAccountsSuccess(accounts),
+            Err(err) => Msg::GetAccountsError(err),
+        });
+        Self {
+            accounts: Vec::new(),
+            query: String::new(),
+            selected: 0,
+            input: NodeRef::default(),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetAccountsSuccess(accounts) => {
+                self.accounts = accounts.into_iter().map(|account| account.name).collect();
+                true
+            }
+            Msg::GetAccountsError(err) => {
+                log::error!("Cannot get accounts: {}", err);
+                false
+            }
+            Msg::Query(query) => {
+                self.query = query;
+                self.selected = 0;
+                true
+            }
+            Msg::Move(step) => {
+                let len = self.items().len() as isize;
+                if len > 0 {
+                    self.selected = (self.selected as isize + step).rem_euclid(len) as usize;
+                }
+                true
+            }
+            Msg::Go(index) => {
+                if let Some((route, _)) = self.items().into_iter().nth(index) {
+                    api::history().push(route);
+                    ctx.props().onclose.emit(());
+                }
+                false
+            }
+        }
+    }
+
+    fn rendered(&mut self, _ctx: &Context<Self>, first_render: bool) {
+        if first_render {
+            if let Some(input) = self.input.cast::<HtmlInputElement>() {
+                let _ = input.focus();
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let onclose = ctx.props().onclose.clone();
+        let selected = self.selected;
+        let onkeydown = ctx
+            .link()
+            .batch_callback(move |e: KeyboardEvent| match e.key().as_str() {
+                "ArrowDown" => {
+                    e.prevent_default();
+                    Some(Msg::Move(1))
+                }
+                "ArrowUp" => {
+                    e.prevent_default();
+                    Some(Msg::Move(-1))
+                }
+                "Enter" => Some(Msg::Go(selected)),
+                "Escape" => {
+                    onclose.emit(());
+                    None
+                }
+                _ => None,
+            });
+        let oninput = ctx.link().callback(|e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            Msg::Query(input.value())
+        });
+        let items: Vec<_> = self
+            .items()
+            .into_iter()
+            .enumerate()
+            .map(|(index, (route, title))| {
+                let class = match route {
+                    Route::Account { .. } => "account",
+                    _ => "page",
+                };
+                let class = if index == selected {
+                    classes!(class, "selected")
+                } else {
+                    classes!(class)
+                };
+                let onclick = ctx.link().callback(move |_| Msg::Go(index));
+                html! {<li {class} {onclick}>{title}</li>}
+            })
+            .collect();
+        let onclick_backdrop = ctx.props().onclose.reform(|_: MouseEvent| ());
+        html! {
+            <div class="palette-backdrop" onclick={onclick_backdrop}>
+                <div class="palette" onclick={Callback::from(|e: MouseEvent| e.stop_propagation())}>
+                    <input ref={self.input.clone()} type="search" placeholder={i18n::tr("Jump to an account or page")}
+                        value={self.query.clone()} {oninput} {onkeydown}/>
+                    <ul>
+                        {items}
+                    </ul>
+                </div>
+            </div>
+        }
+    }
+}
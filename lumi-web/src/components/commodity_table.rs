@@ -0,0 +1,201 @@
+use crate::api::{self, FetchState};
+use crate::components::AccountRef;
+use crate::i18n;
+use anyhow::Error;
+use chrono::Datelike;
+use lumi_server_defs::{CommodityDetails, Price};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use yew::context::ContextHandle;
+use yew::prelude::*;
+
+const CHART_WIDTH: f64 = 600.0;
+const CHART_HEIGHT: f64 = 160.0;
+
+#[derive(Properties, Clone, PartialEq, Eq)]
+pub struct Props {
+    pub name: String,
+}
+
+pub enum Msg {
+    GetCommodity,
+    GetCommoditySuccess(CommodityDetails),
+    GetCommodityError(Error),
+}
+
+pub struct CommodityTable {
+    fetch_state: FetchState<CommodityDetails>,
+    _handle: ContextHandle<i64>,
+}
+
+fn values_to_html(values: &HashMap<String, Decimal>) -> Html {
+    let mut entries: Vec<_> = values.iter().collect();
+    entries.sort_by_key(|(c, _)| *c);
+    entries
+        .into_iter()
+        .map(|(c, n)| html! {<div>{i18n::amount(n, c)}</div>})
+        .collect()
+}
+
+// Prices quoted in the currency of the latest price, as a line over time.
+fn price_chart(prices: &[Price]) -> Html {
+    let currency = match prices.last() {
+        Some(latest) => &latest.currency,
+        None => return html! {},
+    };
+    let prices: Vec<&Price> = prices.iter().filter(|p| &p.currency == currency).collect();
+    if prices.len() < 2 {
+        return html! {};
+    }
+    let (first, last) = (prices[0], prices[prices.len() - 1]);
+    let min = prices.iter().map(|p| p.number).min().unwrap();
+    let max = prices.iter().map(|p| p.number).max().unwrap();
+    let days = (last.date.num_days_from_ce() - first.date.num_days_from_ce()) as f64;
+    let range = (max - min).to_f64().unwrap_or_default();
+    let points: Vec<String> = prices
+        .iter()
+        .map(|p| {
+            let x = if days > 0.0 {
+                (p.date.num_days_from_ce() - first.date.num_days_from_ce()) as f64 / days
+                    * CHART_WIDTH
+            } else {
+                CHART_WIDTH / 2.0
+            };
+            let y = if range > 0.0 {
+                CHART_HEIGHT - (p.number - min).to_f64().unwrap_or_default() / range * CHART_HEIGHT
+            } else {
+                CHART_HEIGHT / 2.0
+            };
+            format!("{:.1},{:.1}", x, y)
+        })
+        .collect();
+    html! {
+        <div class="price-chart">
+            <div class="mono">{i18n::amount(&max, currency)}</div>
+            <svg viewBox={format!("0 0 {} {}", CHART_WIDTH, CHART_HEIGHT)} preserveAspectRatio="none">
+                <polyline points={points.join(" ")} />
+            </svg>
+            <div class="mono">{i18n::amount(&min, currency)}</div>
+            <div class="mono range">
+                <span>{i18n::date(&first.date)}</span>
+                <span>{i18n::date(&last.date)}</span>
+            </div>
+        </div>
+    }
+}
+
+impl Component for CommodityTable {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let (_, handle) = ctx
+            .link()
+            .context::<i64>(ctx.link().callback(|_| Msg::GetCommodity))
+            .expect("context to be set");
+        ctx.link().send_message(Msg::GetCommodity);
+        Self {
+            fetch_state: FetchState::NotStarted,
+            _handle: handle,
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>) -> bool {
+        ctx.link().send_message(Msg::GetCommodity);
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetCommodityError(err) => {
+                self.fetch_state = FetchState::Failed(err);
+                true
+            }
+            Msg::GetCommoditySuccess(details) => {
+                self.fetch_state = FetchState::Success(details);
+                true
+            }
+            Msg::GetCommodity => {
+                self.fetch_state = FetchState::Fetching;
+                api::get_commodity(&ctx.props().name, ctx, |result| match result {
+                    Ok(details) => Msg::GetCommoditySuccess(details),
+                    Err(err) => Msg::GetCommodityError(err),
+                });
+                false
+            }
+        }
+    }
+
+    fn view(&self, _ctx: &Context<Self>) -> Html {
+        match self.fetch_state {
+            FetchState::Failed(ref reason) => html! {<p class="error">{reason.to_string()}</p>},
+            FetchState::Fetching => html! {<p>{i18n::tr("loading")}</p>},
+            FetchState::NotStarted => html! {<p>{i18n::tr("not started")}</p>},
+            FetchState::Success(ref details) => {
+                let commodity = &details.commodity;
+                let mut rows = vec![
+                    html! {
+                        <tr><th class="left">{i18n::tr("Units")}</th><td class="mono">{i18n::amount(&commodity.units, &commodity.name)}</td></tr>
+                    },
+                    html! {
+                        <tr><th class="left">{i18n::tr("Precision")}</th><td class="mono">{commodity.precision.to_string()}</td></tr>
+                    },
+                ];
+                if let Some(price) = &commodity.latest_price {
+                    rows.push(html! {
+                        <tr>
+                            <th class="left">{i18n::tr("Latest Price")}</th>
+                            <td class="mono">{i18n::amount(&price.number, &price.currency)}{" ("}{i18n::date(&price.date)}{")"}</td>
+                        </tr>
+                    });
+                }
+                for (key, value) in &commodity.meta {
+                    rows.push(html! {
+                        <tr><th class="left">{key}</th><td class="mono">{value}</td></tr>
+                    });
+                }
+                let holdings = if details.holdings.is_empty() {
+                    html! {}
+                } else {
+                    let holding_rows: Html = details
+                        .holdings
+                        .iter()
+                        .map(|holding| {
+                            html! {
+                                <tr>
+                                    <td class="left"><AccountRef account={holding.account.clone()}/></td>
+                                    <td class="mono right">{i18n::amount(&holding.units, &commodity.name)}</td>
+                                    <td class="mono right">{values_to_html(&holding.book_value)}</td>
+                                    <td class="mono right">{values_to_html(&holding.market_value)}</td>
+                                </tr>
+                            }
+                        })
+                        .collect();
+                    html! {
+                        <div class="card">
+                            <table class="holdings">
+                                <tr>
+                                    <th class="left">{i18n::tr("Account")}</th>
+                                    <th class="right">{i18n::tr("Units")}</th>
+                                    <th class="right">{i18n::tr("Book Value")}</th>
+                                    <th class="right">{i18n::tr("Market Value")}</th>
+                                </tr>
+                                {holding_rows}
+                            </table>
+                        </div>
+                    }
+                };
+                html! {
+                    <>
+                        <div class="card commodity-header">
+                            <table class="info">{rows}</table>
+                            {price_chart(&details.prices)}
+                        </div>
+                        {holdings}
+                    </>
+                }
+            }
+        }
+    }
+}
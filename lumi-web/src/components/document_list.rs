@@ -0,0 +1,106 @@
+use crate::api::{self, FetchState};
+use crate::i18n;
+use anyhow::Error;
+use lumi_server_defs::{Document, DocumentOptions};
+use yew::context::ContextHandle;
+use yew::prelude::*;
+
+#[derive(Properties, Clone, PartialEq, Eq)]
+pub struct Props {
+    pub account: String,
+}
+
+pub enum Msg {
+    GetDocuments,
+    GetDocumentsSuccess(Vec<Document>),
+    GetDocumentsError(Error),
+}
+
+pub struct DocumentList {
+    fetch_state: FetchState<Vec<Document>>,
+    _handle: ContextHandle<i64>,
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit(|c| c == '/' || c == '\\')
+        .next()
+        .unwrap_or(path)
+}
+
+impl Component for DocumentList {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let (_, handle) = ctx
+            .link()
+            .context::<i64>(ctx.link().callback(|_| Msg::GetDocuments))
+            .expect("context to be set");
+        ctx.link().send_message(Msg::GetDocuments);
+        Self {
+            fetch_state: FetchState::NotStarted,
+            _handle: handle,
+        }
+    }
+
+    fn changed(&mut self, ctx: &Context<Self>) -> bool {
+        ctx.link().send_message(Msg::GetDocuments);
+        true
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetDocumentsError(err) => {
+                self.fetch_state = FetchState::Failed(err);
+                true
+            }
+            Msg::GetDocumentsSuccess(documents) => {
+                self.fetch_state = FetchState::Success(documents);
+                true
+            }
+            Msg::GetDocuments => {
+                self.fetch_state = FetchState::Fetching;
+                let options = DocumentOptions {
+                    account: Some(ctx.props().account.clone()),
+                };
+                api::get_documents(&options, ctx, |result| match result {
+                    Ok(documents) => Msg::GetDocumentsSuccess(documents),
+                    Err(err) => Msg::GetDocumentsError(err),
+                });
+                false
+            }
+        }
+    }
+
+    fn view(&self, _ctx: &Context<Self>) -> Html {
+        match self.fetch_state {
+            FetchState::Failed(ref reason) => html! {<p class="error">{reason.to_string()}</p>},
+            FetchState::Fetching | FetchState::NotStarted => html! {},
+            FetchState::Success(ref documents) if documents.is_empty() => html! {},
+            FetchState::Success(ref documents) => {
+                let rows: Html = documents
+                    .iter()
+                    .map(|document| {
+                        html! {
+                            <tr>
+                                <td class="left mono date">{i18n::date(&document.date)}</td>
+                                <td class="left">
+                                    <a href={api::document_url(&document.path)} target="_blank" title={document.path.clone()}>
+                                        {"📎 "}{file_name(&document.path)}
+                                    </a>
+                                </td>
+                                <td class="left">{document.description.clone().unwrap_or_default()}</td>
+                            </tr>
+                        }
+                    })
+                    .collect();
+                html! {
+                    <div class="card documents">
+                        <h3>{i18n::tr("Documents")}</h3>
+                        <table>{rows}</table>
+                    </div>
+                }
+            }
+        }
+    }
+}
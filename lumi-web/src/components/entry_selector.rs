@@ -1,3 +1,4 @@
+use crate::i18n;
 use crate::route::Route;
 use lumi_server_defs::{FilterOptions, DEFAULT_ENTRIES_PER_PAGE};
 use yew::prelude::*;
@@ -53,11 +54,11 @@ pub fn entry_selector(props: &Props) -> Html {
     };
     let menu_button = if *show_menu {
         html! {
-            <span onclick={show_menu_onclick} class="button selected">{props.entries}{" rows"}<div class="arrow-up"></div></span>
+            <span onclick={show_menu_onclick} class="button selected">{i18n::trf("{} rows", &[&props.entries])}<div class="arrow-up"></div></span>
         }
     } else {
         html! {
-            <span onclick={show_menu_onclick} class="button">{props.entries}{" rows"}<div class="arrow-down"></div></span>
+            <span onclick={show_menu_onclick} class="button">{i18n::trf("{} rows", &[&props.entries])}<div class="arrow-down"></div></span>
         }
     };
     html! {
@@ -1,4 +1,5 @@
 use crate::api::{self, FetchState, LumiErrors};
+use crate::i18n;
 use anyhow::Error;
 use lumi::ErrorLevel;
 use yew::{context::ContextHandle, prelude::*};
@@ -54,15 +55,15 @@ impl Component for ErrorTable {
 
     fn view(&self, _ctx: &yew::Context<Self>) -> yew::Html {
         match self.fetch_state {
-            FetchState::Failed(ref reason) => html! {<p>{format!("failed {}", reason)}</p>},
-            FetchState::Fetching => html! {<p>{"loading"}</p>},
-            FetchState::NotStarted => html! {<p>{"not started"}</p>},
+            FetchState::Failed(ref reason) => html! {<p class="error">{reason.to_string()}</p>},
+            FetchState::Fetching => html! {<p>{i18n::tr("loading")}</p>},
+            FetchState::NotStarted => html! {<p>{i18n::tr("not started")}</p>},
             FetchState::Success(ref errors) => {
                 let error_list: Vec<_> = errors.iter().map(|error| {
                     let error_type = match error.level {
-                        ErrorLevel::Error => html!{<span class="error">{"Error"}</span>},
-                        ErrorLevel::Info => html!{<span class="info">{"Info"}</span>},
-                        ErrorLevel::Warning => html!{<span class="warning">{"Warning"}</span>},
+                        ErrorLevel::Error => html!{<span class="error">{i18n::tr("Error")}</span>},
+                        ErrorLevel::Info => html!{<span class="info">{i18n::tr("Info")}</span>},
+                        ErrorLevel::Warning => html!{<span class="warning">{i18n::tr("Warning")}</span>},
                     };
                     html!{
                         <>
@@ -1,5 +1,6 @@
 use crate::api::{self, FetchState};
 use crate::components::AccountRef;
+use crate::i18n;
 use anyhow::Error;
 use chrono::MIN_DATE;
 use lumi_server_defs::Position;
@@ -63,17 +64,17 @@ impl Component for HoldingTable {
 
     fn view(&self, _ctx: &yew::Context<Self>) -> yew::Html {
         match self.fetch_state {
-            FetchState::Failed(ref reason) => html! {<p>{format!("failed {}", reason)}</p>},
-            FetchState::Fetching => html! {<p>{"loading"}</p>},
-            FetchState::NotStarted => html! {<p>{"not started"}</p>},
+            FetchState::Failed(ref reason) => html! {<p class="error">{reason.to_string()}</p>},
+            FetchState::Fetching => html! {<p>{i18n::tr("loading")}</p>},
+            FetchState::NotStarted => html! {<p>{i18n::tr("not started")}</p>},
             FetchState::Success(ref holdings) => {
                 let mut rows: Vec<Html> = vec![html! {
                     <tr>
-                        <th class={"left"}>{"Account"}</th>
-                        <th class={"right"}>{"Amount"}</th>
-                        <th class={"right"}>{"Cost"}</th>
-                        <th class={"right"}>{"Acquisition Date"}</th>
-                        <th class={"right"}>{"Book Value"}</th>
+                        <th class={"left"}>{i18n::tr("Account")}</th>
+                        <th class={"right"}>{i18n::tr("Amount")}</th>
+                        <th class={"right"}>{i18n::tr("Cost")}</th>
+                        <th class={"right"}>{i18n::tr("Acquisition Date")}</th>
+                        <th class={"right"}>{i18n::tr("Book Value")}</th>
                     </tr>
                 }];
                 let mut entries = holdings.iter().collect::<Vec<_>>();
@@ -99,20 +100,20 @@ impl Component for HoldingTable {
                             rows.push(html!{
                                 <tr>
                                     <td class={"left"}><AccountRef account={account.clone()}/></td>
-                                    <td class={"mono right"}>{position.number}{" "}{&position.currency}</td>
-                                    <td class={"mono right"}>{&cost.amount}</td>
-                                    <td class={"mono right"}>{&cost.date}</td>
-                                    <td class={"mono right"}>{position.number*cost.amount.number}{" "}{&cost.amount.currency}</td>
+                                    <td class={"mono right"}>{i18n::amount(&position.number, &position.currency)}</td>
+                                    <td class={"mono right"}>{i18n::amount(&cost.amount.number, &cost.amount.currency)}</td>
+                                    <td class={"mono right"}>{i18n::date(&cost.date)}</td>
+                                    <td class={"mono right"}>{i18n::amount(&(position.number * cost.amount.number), &cost.amount.currency)}</td>
                                 </tr>
                             })
                         } else {
                             rows.push(html!{
                                 <tr>
                                     <td class={"left"}><AccountRef account={account.clone()}/></td>
-                                    <td class={"mono right"}>{position.number}{" "}{&position.currency}</td>
+                                    <td class={"mono right"}>{i18n::amount(&position.number, &position.currency)}</td>
                                     <td class={"mono right"}></td>
                                     <td class={"mono right"}></td>
-                                    <td class={"mono right"}>{position.number}{" "}{&position.currency}</td>
+                                    <td class={"mono right"}>{i18n::amount(&position.number, &position.currency)}</td>
                                 </tr>
                             })
                         }
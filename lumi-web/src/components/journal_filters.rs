@@ -0,0 +1,105 @@
+use crate::i18n;
+use crate::route::Route;
+use lumi_server_defs::FilterOptions;
+use rust_decimal::Decimal;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+#[derive(Properties, Clone, Debug, PartialEq)]
+pub struct Props {
+    pub show_account_postings: bool,
+}
+
+// (label, flags, exclude_flags)
+const FLAG_CHOICES: [(&str, Option<&str>, Option<&str>); 4] = [
+    ("All", None, None),
+    ("*", Some("*"), None),
+    ("!", Some("!"), None),
+    ("No pad/balance", None, Some("pad,balance")),
+];
+
+fn non_empty(value: String) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+#[function_component(JournalFilters)]
+pub fn journal_filters(props: &Props) -> Html {
+    let location = use_location().unwrap();
+    let history = use_history().unwrap();
+    let route: Route = location.route().unwrap();
+    let query = location.search();
+    let query = query.strip_prefix('?').unwrap_or(&query);
+    let current_option: FilterOptions = serde_urlencoded::from_str(query).unwrap_or_default();
+
+    type Anchor = Link<Route, FilterOptions>;
+    let flag_buttons: Vec<_> = FLAG_CHOICES
+        .iter()
+        .map(|(label, flags, exclude_flags)| {
+            let mut new_option = current_option.clone();
+            new_option.flags = flags.map(str::to_string);
+            new_option.exclude_flags = exclude_flags.map(str::to_string);
+            new_option.page = None;
+            let class = if new_option.flags == current_option.flags
+                && new_option.exclude_flags == current_option.exclude_flags
+            {
+                "button selected"
+            } else {
+                "button"
+            };
+            html! {
+                <Anchor to={route.clone()} query={new_option} classes={class}>{i18n::tr(*label)}</Anchor>
+            }
+        })
+        .collect();
+
+    // Text inputs navigate when they lose focus or on enter, not on every key.
+    let on_change = |update: fn(&mut FilterOptions, String)| {
+        let history = history.clone();
+        let route = route.clone();
+        let current_option = current_option.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut new_option = current_option.clone();
+            update(&mut new_option, input.value());
+            new_option.page = None;
+            let _ = history.push_with_query(route.clone(), new_option);
+        })
+    };
+    let onchange_currency = on_change(|options, value| options.currency = non_empty(value));
+    let onchange_min = on_change(|options, value| options.min_amount = value.trim().parse().ok());
+    let onchange_max = on_change(|options, value| options.max_amount = value.trim().parse().ok());
+
+    let account_postings_toggle = if props.show_account_postings {
+        let mut new_option = current_option.clone();
+        let class = if current_option.account_postings == Some(true) {
+            new_option.account_postings = None;
+            "button selected"
+        } else {
+            new_option.account_postings = Some(true);
+            "button"
+        };
+        html! {
+            <Anchor to={route.clone()} query={new_option} classes={class}>{i18n::tr("This account only")}</Anchor>
+        }
+    } else {
+        html! {}
+    };
+    let amount_value = |amount: Option<Decimal>| amount.map(|n| n.to_string()).unwrap_or_default();
+
+    html! {
+        <div class="journal-filters">
+            <div class="flag-filter">
+                {flag_buttons}
+            </div>
+            <input type="text" class="currency-filter" placeholder={i18n::tr("Currency")}
+                value={current_option.currency.clone().unwrap_or_default()} onchange={onchange_currency}/>
+            <input type="number" class="amount-filter" placeholder={i18n::tr("Min amount")} step="any"
+                value={amount_value(current_option.min_amount)} onchange={onchange_min}/>
+            <input type="number" class="amount-filter" placeholder={i18n::tr("Max amount")} step="any"
+                value={amount_value(current_option.max_amount)} onchange={onchange_max}/>
+            {account_postings_toggle}
+        </div>
+    }
+}
@@ -1,32 +1,49 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::api::{self, FetchState, Journal};
-use crate::components::{EntrySelector, TxnCell};
+use crate::components::{EntrySelector, JournalFilters, TxnCell};
+use crate::i18n;
+use crate::keys;
 use crate::route::Route;
 use anyhow::Error;
-use lumi_server_defs::{FilterOptions, DEFAULT_ENTRIES_PER_PAGE};
+use gloo_events::EventListener;
+use lumi_server_defs::{
+    is_sub_account, FilterOptions, Position, UploadOptions, UploadedDocument,
+    DEFAULT_ENTRIES_PER_PAGE,
+};
 use rust_decimal::Decimal;
 use yew::context::ContextHandle;
 
 use yew::prelude::*;
 use yew_router::components::Link;
-use yew_router::history::{BrowserHistory, History, Location};
+use yew_router::history::{History, Location};
 
 #[derive(Properties, Clone, PartialEq, Eq)]
 pub struct Props {
     pub account: String,
     pub options: String,
+    // files can be dropped onto transactions unless the server is read-only
+    #[prop_or(true)]
+    pub read_only: bool,
 }
 
 struct State {
     options: FilterOptions,
     expand_postings: bool,
+    // rows whose postings are shown differently from `expand_postings`
+    toggled: HashSet<usize>,
+    // the row moved to with j and k
+    selected: Option<usize>,
+    scroll_to_selected: bool,
 }
 pub struct JournalTable {
     state: State,
 
     fetch_state: FetchState<(Journal, usize)>,
+    // reports the reload after an upload
+    on_reload: Callback<i64>,
     _handle: ContextHandle<i64>,
+    _listener: EventListener,
 }
 
 pub enum Msg {
@@ -34,17 +51,51 @@ pub enum Msg {
     GetJournalError(Error),
     GetJournalSuccess(Journal, usize),
     ExpandPostings,
+    ToggleRow(usize),
+    // j and k select a row, Enter expands it, [ and ] change the page
+    Key(String),
+    Upload(usize, web_sys::File),
+    UploadSuccess(UploadedDocument),
+    UploadError(Error),
+}
+
+fn alert(message: &str) {
+    if let Some(window) = web_sys::window() {
+        let _ = window.alert_with_message(message);
+    }
 }
 
 fn change_to_str(changes: &HashMap<String, Decimal>) -> String {
     let descriptions: Vec<String> = changes
         .iter()
         .filter(|(_, n)| !n.is_zero())
-        .map(|(c, n)| format!("{} {}", n, c))
+        .map(|(c, n)| i18n::amount(n, c))
         .collect();
     descriptions.join("\n")
 }
 
+fn lots_to_str(lots: &[Position], book_value: &HashMap<String, Decimal>) -> String {
+    let mut descriptions: Vec<String> = lots
+        .iter()
+        .map(|lot| match &lot.cost {
+            Some(cost) => format!(
+                "{} {{{}, {}}}",
+                i18n::amount(&lot.number, &lot.currency),
+                i18n::amount(&cost.amount.number, &cost.amount.currency),
+                i18n::date(&cost.date)
+            ),
+            None => i18n::amount(&lot.number, &lot.currency),
+        })
+        .collect();
+    descriptions.extend(
+        book_value
+            .iter()
+            .filter(|(_, n)| !n.is_zero())
+            .map(|(c, n)| format!("= {}", i18n::amount(n, c))),
+    );
+    descriptions.join("\n")
+}
+
 impl Component for JournalTable {
     type Message = Msg;
     type Properties = Props;
@@ -56,14 +107,39 @@ impl Component for JournalTable {
             .context::<i64>(ctx.link().callback(|_| Msg::GetJournal))
             .expect("context to be set");
 
+        let link = ctx.link().clone();
+        let listener = keys::on_keydown(move |event| {
+            let key = event.key();
+            let handled = match key.as_str() {
+                "j" | "k" | "[" | "]" => keys::is_shortcut(event),
+                "Enter" => keys::is_enter_shortcut(event),
+                _ => false,
+            };
+            if handled {
+                event.prevent_default();
+                link.send_message(Msg::Key(key));
+            }
+        });
+
+        let on_reload = ctx
+            .link()
+            .context::<Callback<i64>>(Callback::noop())
+            .map(|(callback, _)| callback)
+            .unwrap_or_default();
+
         let options = serde_urlencoded::from_str(&ctx.props().options).unwrap_or_default();
         Self {
             fetch_state: FetchState::NotStarted,
+            on_reload,
             state: State {
                 options,
                 expand_postings: false,
+                toggled: HashSet::new(),
+                selected: None,
+                scroll_to_selected: false,
             },
             _handle: handle,
+            _listener: listener,
         }
     }
 
@@ -81,6 +157,8 @@ impl Component for JournalTable {
             }
             Msg::GetJournalSuccess(journal, total) => {
                 self.fetch_state = FetchState::Success((journal, total));
+                self.state.toggled.clear();
+                self.state.selected = None;
                 true
             }
             Msg::GetJournal => {
@@ -98,40 +176,175 @@ impl Component for JournalTable {
             Msg::ExpandPostings => {
                 log::info!("Msg::ExpandPostings");
                 self.state.expand_postings = !self.state.expand_postings;
+                self.state.toggled.clear();
+                true
+            }
+            Msg::ToggleRow(index) => {
+                if !self.state.toggled.remove(&index) {
+                    self.state.toggled.insert(index);
+                }
                 true
             }
+            Msg::Upload(index, file) => {
+                let txn = match self.fetch_state {
+                    FetchState::Success((ref journal, _)) => match journal.get(index) {
+                        Some(item) => item.txn.clone(),
+                        None => return false,
+                    },
+                    _ => return false,
+                };
+                // The receipt becomes metadata of the transaction, which the
+                // server finds by the source of its first posting. On an
+                // account page it is filed under the posting of that account.
+                let first = match txn.postings().first() {
+                    Some(posting) => posting,
+                    None => return false,
+                };
+                let account = &ctx.props().account;
+                let posting = txn
+                    .postings()
+                    .iter()
+                    .find(|posting| is_sub_account(&posting.account, account))
+                    .unwrap_or(first);
+                let options = UploadOptions {
+                    account: posting.account.to_string(),
+                    date: txn.date(),
+                    filename: file.name(),
+                    src_file: Some(first.src.file.to_string()),
+                    src_line: Some(first.src.start.line),
+                };
+                api::upload_document(&options, file, ctx, |result| match result {
+                    Ok(uploaded) => Msg::UploadSuccess(uploaded),
+                    Err(err) => Msg::UploadError(err),
+                });
+                false
+            }
+            Msg::UploadSuccess(uploaded) => {
+                log::info!("Uploaded {}", uploaded.document.path);
+                self.on_reload.emit(uploaded.refresh.timestamp);
+                false
+            }
+            Msg::UploadError(err) => {
+                alert(&i18n::trf("Cannot upload the document: {}", &[&err]));
+                false
+            }
+            Msg::Key(key) => {
+                let (rows, total) = match self.fetch_state {
+                    FetchState::Success((ref journal, total)) => (journal.len(), total),
+                    _ => return false,
+                };
+                match (key.as_str(), self.state.selected) {
+                    ("j", _) | ("k", _) if rows > 0 => {
+                        let selected = match (key.as_str(), self.state.selected) {
+                            (_, None) => 0,
+                            ("j", Some(index)) => std::cmp::min(index + 1, rows - 1),
+                            (_, Some(index)) => index.saturating_sub(1),
+                        };
+                        self.state.selected = Some(selected);
+                        self.state.scroll_to_selected = true;
+                        true
+                    }
+                    ("Enter", Some(index)) => {
+                        ctx.link().send_message(Msg::ToggleRow(index));
+                        false
+                    }
+                    ("[", _) | ("]", _) => {
+                        let entries = self
+                            .state
+                            .options
+                            .entries
+                            .unwrap_or(DEFAULT_ENTRIES_PER_PAGE);
+                        let total_pages = (total + entries - 1) / entries;
+                        let current_page = self.state.options.page.unwrap_or(1);
+                        let page = if key == "[" {
+                            current_page.saturating_sub(1)
+                        } else {
+                            current_page + 1
+                        };
+                        if page >= 1 && page <= total_pages && page != current_page {
+                            let mut options = self.state.options.clone();
+                            options.page = if page == 1 { None } else { Some(page) };
+                            let history = api::history();
+                            let route: Route = history.location().route().unwrap();
+                            let _ = history.push_with_query(route, options);
+                        }
+                        false
+                    }
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    fn rendered(&mut self, _ctx: &Context<Self>, _first_render: bool) {
+        if !self.state.scroll_to_selected {
+            return;
+        }
+        self.state.scroll_to_selected = false;
+        if let Some(row) = web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.query_selector("table.txn tr.selected").ok())
+            .flatten()
+        {
+            row.scroll_into_view_with_bool(false);
         }
     }
 
     fn view(&self, ctx: &yew::Context<Self>) -> yew::Html {
         match self.fetch_state {
-            FetchState::Failed(ref reason) => html! {<p>{format!("failed {}", reason)}</p>},
-            FetchState::Fetching => html! {<p>{"loading"}</p>},
-            FetchState::NotStarted => html! {<p>{"not started"}</p>},
+            FetchState::Failed(ref reason) => html! {<p class="error">{reason.to_string()}</p>},
+            FetchState::Fetching => html! {<p>{i18n::tr("loading")}</p>},
+            FetchState::NotStarted => html! {<p>{i18n::tr("not started")}</p>},
             FetchState::Success((ref journal, total)) => {
                 log::info!("journal table view, success branch");
                 log::info!("show_postings = {}", self.state.expand_postings);
                 let mut rows = vec![];
                 let props = ctx.props();
+                let show_lots = self.state.options.show_lots == Some(true);
+                let ontoggle = ctx.link().callback(Msg::ToggleRow);
+                let ondropfile = if props.read_only {
+                    None
+                } else {
+                    Some(
+                        ctx.link()
+                            .callback(|(index, file): (usize, web_sys::File)| {
+                                Msg::Upload(index, file)
+                            }),
+                    )
+                };
                 if !props.account.is_empty() {
                     for (index, item) in journal.iter().enumerate() {
                         let change_str = change_to_str(&item.changes);
-                        let balance_str = change_to_str(&item.balance);
+                        let balance_str = if show_lots {
+                            lots_to_str(&item.lots, &item.book_value)
+                        } else {
+                            change_to_str(&item.balance)
+                        };
                         rows.push(html!{
-                        <TxnCell txn={item.txn.clone()} change_balance={(change_str, balance_str)} index={index} show_postings={self.state.expand_postings} />
+                        <TxnCell txn={item.txn.clone()} change_balance={(change_str, balance_str)} index={index}
+                            show_postings={self.state.expand_postings != self.state.toggled.contains(&index)}
+                            selected={self.state.selected == Some(index)} ontoggle={ontoggle.clone()}
+                            ondropfile={ondropfile.clone()} />
                     });
                     }
                 } else {
                     for (index, item) in journal.iter().enumerate() {
                         rows.push(html!{
-                        <TxnCell txn={item.txn.clone()} index={index} show_postings={self.state.expand_postings}/>
+                        <TxnCell txn={item.txn.clone()} index={index}
+                            show_postings={self.state.expand_postings != self.state.toggled.contains(&index)}
+                            selected={self.state.selected == Some(index)} ontoggle={ontoggle.clone()}
+                            ondropfile={ondropfile.clone()} />
                     });
                     }
                 }
                 type Anchor = Link<Route, FilterOptions>;
                 let mut options_change_order = self.state.options.clone();
-                let current_route: Route = BrowserHistory::new().location().route().unwrap();
-                let order_indicator = if options_change_order.old_first == Some(true) {
+                let current_route: Route = api::history().location().route().unwrap();
+                // A static export only has the default options.
+                let is_static = api::is_static();
+                let order_indicator = if is_static {
+                    html! {}
+                } else if options_change_order.old_first == Some(true) {
                     options_change_order.old_first = None;
                     html! {
                         <Anchor to={current_route.clone()} query={options_change_order}><div class="arrow-up"></div></Anchor>
@@ -145,25 +358,25 @@ impl Component for JournalTable {
                 let head = if !props.account.is_empty() {
                     html! {
                         <tr class="head">
-                            <th class="left date">{"Date"}{order_indicator}</th>
-                            <th class="center flag">{"Flag"}</th>
-                            <th class="left">{"Description"}</th>
-                            <th class="right amount">{"Position"}</th>
-                            <th class="right cost">{"Cost"}</th>
-                            <th class="right amount">{"Price"}</th>
-                            <th class="right amount">{"Change"}</th>
-                            <th class="right amount">{"Balance"}</th>
+                            <th class="left date">{i18n::tr("Date")}{order_indicator}</th>
+                            <th class="center flag">{i18n::tr("Flag")}</th>
+                            <th class="left">{i18n::tr("Description")}</th>
+                            <th class="right amount">{i18n::tr("Position")}</th>
+                            <th class="right cost">{i18n::tr("Cost")}</th>
+                            <th class="right amount">{i18n::tr("Price")}</th>
+                            <th class="right amount">{i18n::tr("Change")}</th>
+                            <th class="right amount">{i18n::tr("Balance")}</th>
                         </tr>
                     }
                 } else {
                     html! {
                         <tr class="head">
-                            <th class="left date">{"Date"}{order_indicator}</th>
-                            <th class="center flag">{"Flag"}</th>
-                            <th class="left">{"Description"}</th>
-                            <th class="right amount">{"Position"}</th>
-                            <th class="right cost">{"Cost"}</th>
-                            <th class="right amount">{"Price"}</th>
+                            <th class="left date">{i18n::tr("Date")}{order_indicator}</th>
+                            <th class="center flag">{i18n::tr("Flag")}</th>
+                            <th class="left">{i18n::tr("Description")}</th>
+                            <th class="right amount">{i18n::tr("Position")}</th>
+                            <th class="right cost">{i18n::tr("Cost")}</th>
+                            <th class="right amount">{i18n::tr("Price")}</th>
                         </tr>
                     }
                 };
@@ -276,9 +489,14 @@ impl Component for JournalTable {
                     .entries
                     .unwrap_or(DEFAULT_ENTRIES_PER_PAGE);
                 log::info!("current_entries={}", current_entries);
+                let entry_selector = if is_static {
+                    html! {}
+                } else {
+                    html! {<EntrySelector entries={current_entries}/>}
+                };
                 let row_selector = html! {
                     <div class="row-selector">
-                        <EntrySelector entries={current_entries}/>
+                        {entry_selector}
                         <div class="buttons">
                             {page_buttons}
                         </div>
@@ -292,12 +510,37 @@ impl Component for JournalTable {
                 } else {
                     "button"
                 };
+                let lots_toggle = if !props.account.is_empty() && !is_static {
+                    let mut options_lots = self.state.options.clone();
+                    let class_lots = if show_lots {
+                        options_lots.show_lots = None;
+                        "button selected"
+                    } else {
+                        options_lots.show_lots = Some(true);
+                        "button"
+                    };
+                    let current_route: Route = api::history().location().route().unwrap();
+                    html! {
+                        <Anchor to={current_route} query={options_lots} classes={class_lots}>{i18n::tr("Show Lots")}</Anchor>
+                    }
+                } else {
+                    html! {}
+                };
+                let filters = if is_static {
+                    html! {}
+                } else {
+                    html! {<JournalFilters show_account_postings={!props.account.is_empty()}/>}
+                };
                 html! {
                     <>
                         <div class="txn-table-head">
-                            <span onclick={onclick_expand} class={class_expand}>{"Expand Positions"}</span>
+                            <div class="toggles">
+                                <span onclick={onclick_expand} class={class_expand}>{i18n::tr("Expand Positions")}</span>
+                                {lots_toggle}
+                            </div>
                             {row_selector}
                         </div>
+                        {filters}
                         {table}
                     </>
                 }
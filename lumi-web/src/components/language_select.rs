@@ -0,0 +1,27 @@
+use crate::i18n::{self, Lang, LANGS};
+use web_sys::HtmlSelectElement;
+use yew::prelude::*;
+
+#[function_component(LanguageSelect)]
+pub fn language_select() -> Html {
+    let current = i18n::lang();
+    let onchange = Callback::from(|e: Event| {
+        let select: HtmlSelectElement = e.target_unchecked_into();
+        if let Some(lang) = Lang::from_code(&select.value()) {
+            i18n::set_lang(lang);
+        }
+    });
+    let options: Html = LANGS
+        .iter()
+        .map(|lang| {
+            html! {
+                <option value={lang.code()} selected={*lang == current}>{lang.name()}</option>
+            }
+        })
+        .collect();
+    html! {
+        <select id="language" title={i18n::tr("Language")} {onchange}>
+            {options}
+        </select>
+    }
+}
@@ -1,20 +1,44 @@
+mod account_header;
 mod account_ref;
+mod account_tree;
+mod budget_table;
+mod changes_banner;
+mod command_palette;
+mod commodity_table;
+mod document_list;
 mod entry_selector;
 mod error_table;
 mod holding_table;
+mod journal_filters;
 mod journal_table;
+mod language_select;
+mod options_provider;
 mod refresh_button;
+mod saved_views;
+mod shortcuts;
 mod sidebar;
 mod sidebar_item;
 mod trie_table;
 mod txn_cell;
 
+pub use account_header::AccountHeader;
 pub use account_ref::AccountRef;
+pub use account_tree::AccountTree;
+pub use budget_table::BudgetTable;
+pub use changes_banner::ChangesBanner;
+pub use command_palette::CommandPalette;
+pub use commodity_table::CommodityTable;
+pub use document_list::DocumentList;
 pub use entry_selector::EntrySelector;
 pub use error_table::ErrorTable;
 pub use holding_table::HoldingTable;
+pub use journal_filters::JournalFilters;
 pub use journal_table::JournalTable;
+pub use language_select::LanguageSelect;
+pub use options_provider::OptionsProvider;
 pub use refresh_button::RefreshButton;
+pub use saved_views::SavedViews;
+pub use shortcuts::Shortcuts;
 pub use sidebar::Sidebar;
 pub use trie_table::TrieTable;
 pub use txn_cell::TxnCell;
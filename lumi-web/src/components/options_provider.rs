@@ -0,0 +1,61 @@
+use crate::api;
+use anyhow::Error;
+use lumi_server_defs::ServerOptions;
+use std::rc::Rc;
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    #[prop_or_default]
+    pub children: Children,
+}
+
+pub enum Msg {
+    GetOptions,
+    GetOptionsSuccess(ServerOptions),
+    GetOptionsError(Error),
+}
+
+pub struct OptionsProvider {
+    options: Rc<ServerOptions>,
+}
+
+impl Component for OptionsProvider {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Msg::GetOptions);
+        Self {
+            options: Rc::new(ServerOptions { read_only: true }),
+        }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::GetOptions => {
+                api::get_options(ctx, |result| match result {
+                    Ok(options) => Msg::GetOptionsSuccess(options),
+                    Err(err) => Msg::GetOptionsError(err),
+                });
+                false
+            }
+            Msg::GetOptionsSuccess(options) => {
+                self.options = Rc::new(options);
+                true
+            }
+            Msg::GetOptionsError(err) => {
+                log::error!("Cannot get server options: {}", err);
+                false
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <ContextProvider<Rc<ServerOptions>> context={self.options.clone()}>
+                { for ctx.props().children.iter() }
+            </ContextProvider<Rc<ServerOptions>>>
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::api::{self, FetchState};
+use crate::i18n;
 use anyhow::Error;
 use yew::prelude::*;
 
@@ -47,9 +48,12 @@ impl Component for RefreshButton {
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
+        if api::is_static() {
+            return html! {};
+        }
         let onclick = ctx.link().callback(|_| Msg::Refresh);
         html! {
-            <span id={"refresh"} {onclick}>{"Refresh"}</span>
+            <span id={"refresh"} {onclick}>{i18n::tr("Refresh")}</span>
         }
     }
 }
@@ -0,0 +1,144 @@
+use crate::api;
+use crate::i18n;
+use crate::route::Route;
+use anyhow::Error;
+use lumi_server_defs::View;
+use yew::prelude::*;
+use yew_router::components::Link;
+use yew_router::history::{BrowserHistory, History, Location};
+use yew_router::Routable;
+
+#[derive(Properties, Clone, PartialEq)]
+pub struct Props {
+    // path and query of the current page, used to highlight its view
+    pub current: String,
+    pub read_only: bool,
+}
+
+pub enum Msg {
+    GetViews,
+    GetViewsSuccess(Vec<View>),
+    GetViewsError(Error),
+    Save,
+    Delete(String),
+}
+
+pub struct SavedViews {
+    views: Vec<View>,
+}
+
+fn view_url(view: &View) -> String {
+    if view.query.is_empty() {
+        view.path.clone()
+    } else {
+        format!("{}?{}", view.path, view.query)
+    }
+}
+
+impl Component for SavedViews {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(ctx: &Context<Self>) -> Self {
+        ctx.link().send_message(Msg::GetViews);
+        Self { views: Vec::new() }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        let callback = |result: anyhow::Result<Vec<View>>| match result {
+            Ok(views) => Msg::GetViewsSuccess(views),
+            Err(err) => Msg::GetViewsError(err),
+        };
+        match msg {
+            Msg::GetViews => {
+                api::get_views(ctx, callback);
+                false
+            }
+            Msg::GetViewsSuccess(views) => {
+                self.views = views;
+                true
+            }
+            Msg::GetViewsError(err) => {
+                log::error!("Cannot get views: {}", err);
+                false
+            }
+            Msg::Save => {
+                let name = web_sys::window()
+                    .and_then(|window| {
+                        window
+                            .prompt_with_message(i18n::tr("Name of the view"))
+                            .ok()
+                    })
+                    .flatten()
+                    .map(|name| name.trim().to_string())
+                    .filter(|name| !name.is_empty());
+                if let Some(name) = name {
+                    let location = BrowserHistory::new().location();
+                    let query = location.search();
+                    let view = View {
+                        name,
+                        path: location.pathname(),
+                        query: query.strip_prefix('?').unwrap_or(&query).to_string(),
+                    };
+                    api::save_view(view, ctx, callback);
+                }
+                false
+            }
+            Msg::Delete(name) => {
+                api::delete_view(&name, ctx, callback);
+                false
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        type Anchor = Link<Route, Vec<(String, String)>>;
+        let props = ctx.props();
+        let items: Vec<_> = self
+            .views
+            .iter()
+            .filter_map(|view| {
+                let route = Route::recognize(&view.path)?;
+                let query: Vec<(String, String)> =
+                    serde_urlencoded::from_str(&view.query).unwrap_or_default();
+                let class = if view_url(view) == props.current {
+                    "active"
+                } else {
+                    ""
+                };
+                let delete_button = if props.read_only {
+                    html! {}
+                } else {
+                    let name = view.name.clone();
+                    let onclick = ctx.link().callback(move |_| Msg::Delete(name.clone()));
+                    html! {<span class="delete-view" title={i18n::tr("Delete view")} {onclick}>{"×"}</span>}
+                };
+                Some(html! {
+                    <li {class}>
+                        <Anchor to={route} {query}>
+                            <span>{&view.name}</span>
+                        </Anchor>
+                        {delete_button}
+                    </li>
+                })
+            })
+            .collect();
+        let save_button = if props.read_only {
+            html! {}
+        } else {
+            let onclick = ctx.link().callback(|_| Msg::Save);
+            html! {<li class="save-view"><span {onclick}>{i18n::tr("+ Save view")}</span></li>}
+        };
+        if items.is_empty() && props.read_only {
+            return html! {};
+        }
+        html! {
+            <nav class="saved-views">
+                <ul>
+                    {items}
+                    {save_button}
+                </ul>
+            </nav>
+        }
+    }
+}
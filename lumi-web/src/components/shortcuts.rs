@@ -0,0 +1,93 @@
+use crate::api;
+use crate::components::CommandPalette;
+use crate::keys;
+use crate::route::Route;
+use gloo_events::EventListener;
+use std::cell::Cell;
+use std::rc::Rc;
+use yew::prelude::*;
+use yew_router::history::History;
+
+pub enum Msg {
+    OpenPalette,
+    ClosePalette,
+}
+
+// Shortcuts that work on every page:
+//   g b, g i, g j  go to the balance sheet, income statement or journal
+//   /              focuses the account filter
+//   Ctrl-K         opens the command palette
+// Page specific keys, like j and k in the journal, are handled by the page.
+pub struct Shortcuts {
+    show_palette: bool,
+    _listener: EventListener,
+}
+
+fn go_to(key: &str) -> Option<Route> {
+    match key {
+        "b" => Some(Route::Balance),
+        "i" => Some(Route::Income),
+        "j" => Some(Route::Journal),
+        _ => None,
+    }
+}
+
+impl Component for Shortcuts {
+    type Message = Msg;
+    type Properties = ();
+
+    fn create(ctx: &Context<Self>) -> Self {
+        let link = ctx.link().clone();
+        // Set after `g`, so that the next key picks a page.
+        let pending_go = Rc::new(Cell::new(false));
+        let listener = keys::on_keydown(move |event| {
+            let key = event.key();
+            if (event.ctrl_key() || event.meta_key()) && key == "k" {
+                event.prevent_default();
+                link.send_message(Msg::OpenPalette);
+                return;
+            }
+            if !keys::is_shortcut(event) {
+                return;
+            }
+            if pending_go.replace(false) {
+                if let Some(route) = go_to(&key) {
+                    event.prevent_default();
+                    api::history().push(route);
+                    return;
+                }
+            }
+            match key.as_str() {
+                "g" => {
+                    event.prevent_default();
+                    pending_go.set(true);
+                }
+                "/" => {
+                    event.prevent_default();
+                    keys::focus("account-filter");
+                }
+                _ => {}
+            }
+        });
+        Self {
+            show_palette: false,
+            _listener: listener,
+        }
+    }
+
+    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+        let show_palette = matches!(msg, Msg::OpenPalette);
+        let changed = self.show_palette != show_palette;
+        self.show_palette = show_palette;
+        changed
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        if self.show_palette {
+            let onclose = ctx.link().callback(|_| Msg::ClosePalette);
+            html! {<CommandPalette {onclose}/>}
+        } else {
+            html! {}
+        }
+    }
+}
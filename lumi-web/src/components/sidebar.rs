@@ -1,25 +1,27 @@
 use crate::components::sidebar_item::SidebarItem;
-use crate::route::Route;
-use yew::{function_component, html, use_state_eq, Callback};
+use crate::components::{AccountTree, SavedViews};
+use crate::i18n;
+use crate::route::{self, Route};
+use lumi_server_defs::ServerOptions;
+use std::rc::Rc;
+use yew::{function_component, html, use_context, use_state_eq, Callback};
 use yew_router::history::Location;
 use yew_router::hooks::use_location;
 
 #[function_component(Sidebar)]
 pub fn sidebar() -> Html {
     let always_show = use_state_eq(|| false);
-    let item_info = vec![
-        (Route::Balance, "Balance Sheet"),
-        (Route::Income, "Income Statement"),
-        (Route::Journal, "Journal"),
-        (Route::Holdings, "Holdings"),
-        (Route::Errors, "Errors"),
-    ];
+    let item_info = route::pages();
     let location = use_location().unwrap();
     let current = location.route::<Route>();
+    let current_url = format!("{}{}", location.pathname(), location.search());
+    let read_only = use_context::<Rc<ServerOptions>>()
+        .map(|options| options.read_only)
+        .unwrap_or(true);
     let items: Vec<_> = item_info
         .into_iter()
         .map(|(dest, title)| {
-            html! {<SidebarItem dest={dest} active={current==Some(dest.clone())} title={title}/>}
+            html! {<SidebarItem dest={dest} active={current==Some(dest.clone())} title={i18n::tr(title)}/>}
         })
         .collect();
     let ul = html! {
@@ -52,6 +54,8 @@ pub fn sidebar() -> Html {
             <nav onclick={&hide_self}>
                 {ul}
             </nav>
+            <SavedViews current={current_url} {read_only}/>
+            <AccountTree />
         </div>
         </>
     }
@@ -5,6 +5,7 @@ use yew::prelude::*;
 use yew_router::components::Link;
 
 use crate::api::{self, FetchState, Trie};
+use crate::i18n;
 use crate::route::Route;
 
 use lumi_server_defs::TrieOptions;
@@ -79,9 +80,9 @@ impl Component for TrieTable {
 
     fn view(&self, _ctx: &yew::Context<Self>) -> yew::Html {
         match self.fetch_state {
-            FetchState::Failed(ref reason) => html! {<p>{format!("failed {}", reason)}</p>},
-            FetchState::Fetching => html! {<p>{"loading"}</p>},
-            FetchState::NotStarted => html! {<p>{"not started"}</p>},
+            FetchState::Failed(ref reason) => html! {<p class="error">{reason.to_string()}</p>},
+            FetchState::Fetching => html! {<p>{i18n::tr("loading")}</p>},
+            FetchState::NotStarted => html! {<p>{i18n::tr("not started")}</p>},
             FetchState::Success(ref trie) => {
                 let mut heads = vec![html! {<th/>}];
                 for currency in trie.currencies.iter() {
@@ -116,7 +117,9 @@ impl Component for TrieTable {
                             </td>
                         }];
                         for number in &row.numbers {
-                            cols.push(html! {<td class="mono right">{number}</td>});
+                            cols.push(
+                                html! {<td class="mono right">{i18n::number_str(number)}</td>},
+                            );
                         }
                         html! {<tr>{cols}</tr>}
                     })
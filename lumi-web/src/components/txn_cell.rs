@@ -1,10 +1,12 @@
-use lumi::{Transaction, TxnFlag};
+use lumi::{Meta, Transaction, TxnFlag};
 
+use crate::api;
 use crate::components::AccountRef;
+use crate::i18n;
 use std::rc::Rc;
 use yew::prelude::*;
 
-#[derive(Properties, Clone, PartialEq, Eq)]
+#[derive(Properties, Clone, PartialEq)]
 pub struct Props {
     pub txn: Rc<Transaction>,
     #[prop_or(false)]
@@ -12,15 +14,22 @@ pub struct Props {
     #[prop_or_default]
     pub change_balance: Option<(String, String)>,
     pub index: usize,
+    #[prop_or(false)]
+    pub selected: bool,
+    // called with `index` when the posting dots are clicked
+    #[prop_or_default]
+    pub ontoggle: Callback<usize>,
+    // called with `index` and the file dropped onto the transaction; no drop
+    // target without it
+    #[prop_or_default]
+    pub ondropfile: Option<Callback<(usize, web_sys::File)>>,
 }
 
 pub enum Msg {
     ShowHidePostings,
 }
 
-pub struct TxnCell {
-    show_postings: bool,
-}
+pub struct TxnCell;
 
 fn flag_str(flag: TxnFlag) -> &'static str {
     match flag {
@@ -39,6 +48,11 @@ fn even_odd(index: usize) -> &'static str {
     }
 }
 
+fn row_class(kind: &str, props: &Props) -> String {
+    let selected = if props.selected { " selected" } else { "" };
+    format!("{} {}{}", kind, even_odd(props.index), selected)
+}
+
 fn desc(txn: &Transaction) -> Html {
     if !txn.payee().is_empty() {
         if !txn.narration().is_empty() {
@@ -61,6 +75,22 @@ fn desc(txn: &Transaction) -> Html {
     }
 }
 
+// Paperclips for the `document` metadata of a transaction or posting.
+fn document_links(meta: &Meta) -> Html {
+    if api::is_static() {
+        return html! {};
+    }
+    meta.iter()
+        .filter(|(key, _)| key.to_string() == "document")
+        .map(|(_, (value, _))| {
+            let path = value.to_string().trim_matches('"').to_string();
+            html! {
+                <a class="document" href={api::document_url(&path)} target="_blank" title={path}>{"📎"}</a>
+            }
+        })
+        .collect()
+}
+
 fn balance_view(props: &Props) -> Vec<Html> {
     props.txn.postings().iter().map(|posting| {
         let desc_span = if props.change_balance.is_some() {
@@ -68,7 +98,7 @@ fn balance_view(props: &Props) -> Vec<Html> {
         } else {
             "1"
         };
-        let tr_class = format!("balance {}", even_odd(props.index));
+        let tr_class = row_class("balance", props);
         let extra_td = if props.change_balance.is_some() {
             html! {}
         } else {
@@ -76,17 +106,38 @@ fn balance_view(props: &Props) -> Vec<Html> {
         };
         html! {
             <tr class={tr_class}>
-                <td class={"left mono date"}>{props.txn.date()}</td>
+                <td class={"left mono date"}>{i18n::date(&props.txn.date())}</td>
                 <td class={"center mono flag"}>{"bal"}</td>
                 <td class={"left"} colspan={desc_span}><AccountRef account={posting.account.to_string()} /></td>
-                <td class={"right amount mono"}>{&posting.amount}</td>
+                <td class={"right amount mono"}>{i18n::amount(&posting.amount.number, &posting.amount.currency)}</td>
                 {extra_td}
             </tr>
         }
     }).collect::<Vec<Html>>()
 }
 
-fn posting_view(ctx: &Context<TxnCell>, show_postings: bool) -> Vec<Html> {
+// (ondragover, ondrop) for the row of the transaction
+fn drop_listeners(props: &Props) -> (Option<Callback<DragEvent>>, Option<Callback<DragEvent>>) {
+    let ondropfile = match &props.ondropfile {
+        Some(ondropfile) => ondropfile.clone(),
+        None => return (None, None),
+    };
+    let index = props.index;
+    let ondragover = Callback::from(|e: DragEvent| e.prevent_default());
+    let ondrop = Callback::from(move |e: DragEvent| {
+        e.prevent_default();
+        if let Some(file) = e
+            .data_transfer()
+            .and_then(|data| data.files())
+            .and_then(|files| files.get(0))
+        {
+            ondropfile.emit((index, file));
+        }
+    });
+    (Some(ondragover), Some(ondrop))
+}
+
+fn posting_view(ctx: &Context<TxnCell>) -> Vec<Html> {
     let props = ctx.props();
     let mut result = Vec::new();
     let onclick = ctx.link().callback(|_| Msg::ShowHidePostings);
@@ -96,6 +147,7 @@ fn posting_view(ctx: &Context<TxnCell>, show_postings: bool) -> Vec<Html> {
         <>
             <td class={"left"}>
                 {desc(&props.txn)}
+                {document_links(props.txn.meta())}
             </td>
             <td class={"expand mono right"}>
                 <span onclick={onclick}>{indicators}</span>
@@ -103,11 +155,12 @@ fn posting_view(ctx: &Context<TxnCell>, show_postings: bool) -> Vec<Html> {
         </>
     };
 
-    let tr_class = format!("txn {}", even_odd(props.index));
+    let tr_class = row_class("txn", props);
+    let (ondragover, ondrop) = drop_listeners(props);
     if let Some((change, balance)) = &props.change_balance {
         result.push(html! {
-            <tr class={tr_class}>
-                <td class={"left mono date"}>{props.txn.date()}</td>
+            <tr class={tr_class} {ondragover} {ondrop}>
+                <td class={"left mono date"}>{i18n::date(&props.txn.date())}</td>
                 <td class={"center mono flag"}>{flag_str(props.txn.flag())}</td>
                 {desc}
                 <td colspan={"2"}></td>
@@ -117,15 +170,15 @@ fn posting_view(ctx: &Context<TxnCell>, show_postings: bool) -> Vec<Html> {
         })
     } else {
         result.push(html! {
-            <tr class={tr_class}>
-                <td class={"left mono date"}>{props.txn.date()}</td>
+            <tr class={tr_class} {ondragover} {ondrop}>
+                <td class={"left mono date"}>{i18n::date(&props.txn.date())}</td>
                 <td class={"center mono flag"}>{flag_str(props.txn.flag())}</td>
                 {desc}
                 <td colspan={"2"}></td>
             </tr>
         })
     }
-    let class_hide = if show_postings { "" } else { " hide" };
+    let class_hide = if props.show_postings { "" } else { " hide" };
     let posting_class = format!("posting {}{}", even_odd(props.index), class_hide);
     for posting in props.txn.postings() {
         let price = posting
@@ -136,7 +189,7 @@ fn posting_view(ctx: &Context<TxnCell>, show_postings: bool) -> Vec<Html> {
         let cost = posting
             .cost
             .as_ref()
-            .map(|c| html! {<>{&c.amount}<br/>{c.date}</>})
+            .map(|c| html! {<>{i18n::amount(&c.amount.number, &c.amount.currency)}<br/>{i18n::date(&c.date)}</>})
             .unwrap_or_default();
         let extra_td = if props.change_balance.is_some() {
             html! {<td colspan={"2"}></td>}
@@ -147,8 +200,8 @@ fn posting_view(ctx: &Context<TxnCell>, show_postings: bool) -> Vec<Html> {
             <tr class={&posting_class}>
                 <td></td>
                 <td></td>
-                <td class={"left"}><AccountRef account={posting.account.to_string()} /></td>
-                <td class={"right mono amount"}>{&posting.amount}</td>
+                <td class={"left"}><AccountRef account={posting.account.to_string()} />{document_links(&posting.meta)}</td>
+                <td class={"right mono amount"}>{i18n::amount(&posting.amount.number, &posting.amount.currency)}</td>
                 <td class={"right mono cost"}>{cost}</td>
                 <td class={"right mono amount"}>{price}</td>
                 {extra_td}
@@ -162,22 +215,15 @@ impl Component for TxnCell {
     type Message = Msg;
     type Properties = Props;
 
-    fn create(ctx: &Context<Self>) -> Self {
-        Self {
-            show_postings: ctx.props().show_postings,
-        }
-    }
-
-    fn changed(&mut self, ctx: &Context<Self>) -> bool {
-        self.show_postings = ctx.props().show_postings;
-        true
+    fn create(_ctx: &Context<Self>) -> Self {
+        Self
     }
 
-    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             Msg::ShowHidePostings => {
-                self.show_postings = !self.show_postings;
-                true
+                ctx.props().ontoggle.emit(ctx.props().index);
+                false
             }
         }
     }
@@ -186,7 +232,7 @@ impl Component for TxnCell {
         if ctx.props().txn.flag() == TxnFlag::Balance {
             html! {<> {balance_view(ctx.props())} </>}
         } else {
-            html! {<> {posting_view(ctx, self.show_postings)} </>}
+            html! {<> {posting_view(ctx)} </>}
         }
     }
 }
@@ -0,0 +1,221 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::fmt::Display;
+
+const STORAGE_KEY: &str = "lumi-language";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    En,
+    De,
+}
+
+pub const LANGS: [Lang; 2] = [Lang::En, Lang::De];
+
+// Translations keyed by the English text. Messages with `{}` are filled in
+// by `trf`. Missing entries fall back to English.
+const DE: &[(&str, &str)] = &[
+    ("Balance Sheet", "Bilanz"),
+    ("Income Statement", "Erfolgsrechnung"),
+    ("Income", "Erträge"),
+    ("Journal", "Journal"),
+    ("Budget", "Budget"),
+    ("Holdings", "Bestände"),
+    ("Errors", "Fehler"),
+    ("read-only", "schreibgeschützt"),
+    ("Refresh", "Aktualisieren"),
+    ("loading", "wird geladen"),
+    ("not started", "nicht gestartet"),
+    ("Language", "Sprache"),
+    ("Date", "Datum"),
+    ("Flag", "Flag"),
+    ("Description", "Beschreibung"),
+    ("Position", "Position"),
+    ("Cost", "Kosten"),
+    ("Price", "Preis"),
+    ("Change", "Änderung"),
+    ("Balance", "Saldo"),
+    ("Expand Positions", "Positionen aufklappen"),
+    ("Show Lots", "Lots anzeigen"),
+    ("{} rows", "{} Zeilen"),
+    ("All", "Alle"),
+    ("No pad/balance", "Ohne pad/balance"),
+    ("This account only", "Nur dieses Konto"),
+    ("Currency", "Währung"),
+    ("Min amount", "Mindestbetrag"),
+    ("Max amount", "Höchstbetrag"),
+    ("Account", "Konto"),
+    ("Amount", "Betrag"),
+    ("Acquisition Date", "Kaufdatum"),
+    ("Book Value", "Buchwert"),
+    ("Open", "Eröffnet"),
+    ("since {}", "seit {}"),
+    ("Currencies", "Währungen"),
+    ("Last Transaction", "Letzte Buchung"),
+    ("Last Balance", "Letzte Saldoprüfung"),
+    ("Budgeted", "Budgetiert"),
+    ("Actual", "Ist"),
+    ("Remaining", "Verbleibend"),
+    ("Progress", "Fortschritt"),
+    ("Used", "Verbraucht"),
+    ("Error", "Fehler"),
+    ("Info", "Info"),
+    ("Warning", "Warnung"),
+    ("Filter accounts", "Konten filtern"),
+    ("{} postings", "{} Buchungszeilen"),
+    ("1 new transaction", "1 neue Buchung"),
+    ("{} new transactions", "{} neue Buchungen"),
+    ("+ Save view", "+ Ansicht speichern"),
+    ("Delete view", "Ansicht löschen"),
+    ("Name of the view", "Name der Ansicht"),
+    ("Jump to an account or page", "Zu Konto oder Seite springen"),
+    ("Documents", "Dokumente"),
+    ("Units", "Einheiten"),
+    ("Precision", "Genauigkeit"),
+    ("Latest Price", "Letzter Preis"),
+    ("Market Value", "Marktwert"),
+    (
+        "Cannot upload the document: {}",
+        "Das Dokument kann nicht hochgeladen werden: {}",
+    ),
+];
+
+impl Lang {
+    pub fn code(self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::De => "de",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Lang::En => "English",
+            Lang::De => "Deutsch",
+        }
+    }
+
+    // Accepts tags like `de-CH`.
+    pub fn from_code(code: &str) -> Option<Lang> {
+        let primary = code.split('-').next()?.to_lowercase();
+        LANGS.iter().copied().find(|lang| lang.code() == primary)
+    }
+
+    fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Lang::En => &[],
+            Lang::De => DE,
+        }
+    }
+
+    // (decimal separator, thousands separator)
+    fn separators(self) -> (char, char) {
+        match self {
+            Lang::En => ('.', ','),
+            Lang::De => (',', '.'),
+        }
+    }
+
+    fn date_format(self) -> &'static str {
+        match self {
+            Lang::En => "%Y-%m-%d",
+            Lang::De => "%d.%m.%Y",
+        }
+    }
+}
+
+fn storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+// The saved setting wins over the browser's preferred language, which is the
+// first entry of its Accept-Language header.
+fn detect() -> Lang {
+    let saved = storage().and_then(|storage| storage.get_item(STORAGE_KEY).ok().flatten());
+    let browser = web_sys::window().and_then(|window| window.navigator().language());
+    saved
+        .into_iter()
+        .chain(browser)
+        .find_map(|code| Lang::from_code(&code))
+        .unwrap_or(Lang::En)
+}
+
+thread_local! {
+    static LANG: Lang = detect();
+}
+
+pub fn lang() -> Lang {
+    LANG.with(|lang| *lang)
+}
+
+// Saves the setting and reloads the page, so that every component renders
+// in the new language.
+pub fn set_lang(lang: Lang) {
+    if let Some(storage) = storage() {
+        let _ = storage.set_item(STORAGE_KEY, lang.code());
+    }
+    if let Some(window) = web_sys::window() {
+        let _ = window.location().reload();
+    }
+}
+
+pub fn tr(text: &'static str) -> &'static str {
+    lang()
+        .catalog()
+        .iter()
+        .find(|(en, _)| *en == text)
+        .map_or(text, |(_, translated)| *translated)
+}
+
+// Translates `text` and fills its `{}` placeholders in order.
+pub fn trf(text: &'static str, args: &[&dyn Display]) -> String {
+    let mut result = String::new();
+    let mut parts = tr(text).split("{}");
+    if let Some(first) = parts.next() {
+        result.push_str(first);
+    }
+    for (index, part) in parts.enumerate() {
+        if let Some(arg) = args.get(index) {
+            result.push_str(&arg.to_string());
+        }
+        result.push_str(part);
+    }
+    result
+}
+
+// Keeps the number's own precision and only changes the separators.
+pub fn number(n: &Decimal) -> String {
+    let (decimal, group) = lang().separators();
+    let digits = n.abs().to_string();
+    let (int, frac) = digits.split_once('.').unwrap_or((&digits, ""));
+    let mut result = String::new();
+    if n.is_sign_negative() && !n.is_zero() {
+        result.push('-');
+    }
+    for (index, c) in int.chars().enumerate() {
+        if index > 0 && (int.len() - index) % 3 == 0 {
+            result.push(group);
+        }
+        result.push(c);
+    }
+    if !frac.is_empty() {
+        result.push(decimal);
+        result.push_str(frac);
+    }
+    result
+}
+
+// For numbers the server already formatted, like in the trie tables.
+pub fn number_str(s: &str) -> String {
+    s.parse::<Decimal>()
+        .map(|n| number(&n))
+        .unwrap_or_else(|_| s.to_string())
+}
+
+pub fn amount(n: &Decimal, currency: &str) -> String {
+    format!("{} {}", number(n), currency)
+}
+
+pub fn date(date: &NaiveDate) -> String {
+    date.format(lang().date_format()).to_string()
+}
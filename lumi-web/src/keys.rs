@@ -0,0 +1,51 @@
+use gloo_events::EventListener;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, HtmlElement, KeyboardEvent};
+
+// Listens to key presses anywhere on the page until the listener is dropped.
+pub fn on_keydown<F>(callback: F) -> EventListener
+where
+    F: Fn(&KeyboardEvent) + 'static,
+{
+    let document = web_sys::window().unwrap().document().unwrap();
+    EventListener::new(&document, "keydown", move |event| {
+        if let Some(event) = event.dyn_ref::<KeyboardEvent>() {
+            callback(event)
+        }
+    })
+}
+
+fn target_tag(event: &KeyboardEvent) -> String {
+    event
+        .target()
+        .and_then(|target| target.dyn_into::<Element>().ok())
+        .map(|element| element.tag_name())
+        .unwrap_or_default()
+}
+
+// Single-key shortcuts are ignored while the user types into a form field,
+// and modified keys are left to the browser.
+pub fn is_shortcut(event: &KeyboardEvent) -> bool {
+    !event.default_prevented()
+        && !event.ctrl_key()
+        && !event.meta_key()
+        && !event.alt_key()
+        && !matches!(target_tag(event).as_str(), "INPUT" | "TEXTAREA" | "SELECT")
+}
+
+// Enter on a focused link or button should still activate it.
+pub fn is_enter_shortcut(event: &KeyboardEvent) -> bool {
+    event.key() == "Enter"
+        && is_shortcut(event)
+        && !matches!(target_tag(event).as_str(), "A" | "BUTTON")
+}
+
+pub fn focus(id: &str) {
+    if let Some(element) = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.get_element_by_id(id))
+        .and_then(|element| element.dyn_into::<HtmlElement>().ok())
+    {
+        let _ = element.focus();
+    }
+}
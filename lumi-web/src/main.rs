@@ -1,6 +1,8 @@
 mod api;
 mod app;
 mod components;
+mod i18n;
+mod keys;
 mod route;
 
 fn main() {
@@ -6,14 +6,30 @@ pub enum Route {
     Holdings,
     #[at("/account/:name")]
     Account { name: String },
+    #[at("/commodity/:name")]
+    Commodity { name: String },
     #[at("/journal")]
     Journal,
     #[at("/income")]
     Income,
     #[at("/errors")]
     Errors,
+    #[at("/budget")]
+    Budget,
     #[at("/balance_sheet")]
     Balance,
     #[at("/")]
     Index,
 }
+
+// The pages listed in the sidebar and the command palette.
+pub fn pages() -> Vec<(Route, &'static str)> {
+    vec![
+        (Route::Balance, "Balance Sheet"),
+        (Route::Income, "Income Statement"),
+        (Route::Journal, "Journal"),
+        (Route::Budget, "Budget"),
+        (Route::Holdings, "Holdings"),
+        (Route::Errors, "Errors"),
+    ]
+}
@@ -0,0 +1,54 @@
+#[cfg(feature = "web-ui")]
+use crate::embedded;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use warp::filters::fs::File;
+use warp::filters::BoxedFilter;
+use warp::reply::Response;
+use warp::{Filter, Rejection, Reply};
+
+pub const PAGES: [&str; 8] = [
+    "errors",
+    "holdings",
+    "account",
+    "journal",
+    "income",
+    "balance_sheet",
+    "budget",
+    "commodity",
+];
+
+fn disk_routes(dir: PathBuf) -> impl Filter<Extract = (File,), Error = Rejection> + Clone {
+    let index = dir.join("index.html");
+    let root_index = warp::path::end().and(warp::fs::file(index.clone()));
+
+    let pages: HashSet<&str> = PAGES.into_iter().collect();
+    let page = warp::path::param()
+        .and_then(move |path: String| {
+            let is_page = pages.contains(path.as_str());
+            async move {
+                if is_page {
+                    Ok(())
+                } else {
+                    Err(warp::reject::not_found())
+                }
+            }
+        })
+        .untuple_one()
+        .and(warp::fs::file(index));
+    warp::get().and(root_index.or(warp::fs::dir(dir)).unify().or(page).unify())
+}
+
+pub fn routes(web_dir: Option<&str>) -> BoxedFilter<(Response,)> {
+    match web_dir {
+        Some(dir) => disk_routes(PathBuf::from(dir))
+            .map(|file: File| file.into_response())
+            .boxed(),
+        #[cfg(feature = "web-ui")]
+        None => embedded::routes().boxed(),
+        #[cfg(not(feature = "web-ui"))]
+        None => warp::any()
+            .and_then(|| async { Err::<Response, _>(warp::reject::not_found()) })
+            .boxed(),
+    }
+}
@@ -0,0 +1,315 @@
+use crate::directives::Directive;
+use crate::handlers::is_sub_account;
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use lumi::{Ledger, TxnFlag};
+use lumi_server_defs::{BudgetReport, BudgetRow};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interval {
+    Daily,
+    Weekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Budget {
+    pub date: NaiveDate,
+    pub account: String,
+    pub interval: Interval,
+    pub number: Decimal,
+    pub currency: String,
+}
+
+fn days_in_month(year: i32, month: u32) -> i64 {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    let first = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+    let next = NaiveDate::from_ymd_opt(next_year, next_month, 1).unwrap();
+    (next - first).num_days()
+}
+
+impl Interval {
+    fn parse(s: &str) -> Option<Interval> {
+        match s {
+            "daily" => Some(Interval::Daily),
+            "weekly" => Some(Interval::Weekly),
+            "monthly" => Some(Interval::Monthly),
+            "quarterly" => Some(Interval::Quarterly),
+            "yearly" => Some(Interval::Yearly),
+            _ => None,
+        }
+    }
+
+    fn days_containing(&self, date: NaiveDate) -> i64 {
+        match self {
+            Interval::Daily => 1,
+            Interval::Weekly => 7,
+            Interval::Monthly => days_in_month(date.year(), date.month()),
+            Interval::Quarterly => {
+                let first_month = (date.month() - 1) / 3 * 3 + 1;
+                (first_month..first_month + 3)
+                    .map(|month| days_in_month(date.year(), month))
+                    .sum()
+            }
+            Interval::Yearly => (1..=12)
+                .map(|month| days_in_month(date.year(), month))
+                .sum(),
+        }
+    }
+}
+
+// The arguments of `custom`, with quotes removed:
+// "budget" Expenses:Food "monthly" 400.00 USD
+fn parse_budget(date: NaiveDate, args: &[&str]) -> Result<Budget, String> {
+    let (account, interval, number, currency) = match args {
+        ["budget", account, interval, number, currency] => (account, interval, number, currency),
+        _ => return Err(format!("not a budget entry: {}", args.join(" "))),
+    };
+    let interval =
+        Interval::parse(interval).ok_or_else(|| format!("invalid budget interval {}", interval))?;
+    let number = number
+        .parse::<Decimal>()
+        .map_err(|e| format!("invalid number {}: {}", number, e))?;
+    Ok(Budget {
+        date,
+        account: account.to_string(),
+        interval,
+        number,
+        currency: currency.to_string(),
+    })
+}
+
+fn parse_budget_line(line: &str) -> Result<Budget, String> {
+    // 2021-01-01 custom "budget" Expenses:Food "monthly" 400.00 USD
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.len() < 2 || tokens[1] != "custom" {
+        return Err(format!("not a budget entry: {}", line));
+    }
+    let date = NaiveDate::parse_from_str(tokens[0], "%Y-%m-%d")
+        .map_err(|e| format!("invalid date {}: {}", tokens[0], e))?;
+    let args: Vec<&str> = tokens[2..].iter().map(|t| t.trim_matches('"')).collect();
+    parse_budget(date, &args)
+}
+
+fn load_budget_file(path: &str) -> Vec<Budget> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            log::warn!("Cannot read budget file {}: {}", path, e);
+            return Vec::new();
+        }
+    };
+    let mut budgets = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        match parse_budget_line(line) {
+            Ok(budget) => budgets.push(budget),
+            Err(msg) => log::warn!("{}:{}: {}", path, index + 1, msg),
+        }
+    }
+    budgets
+}
+
+// `custom "budget"` entries of the ledger itself.
+fn ledger_budgets(directives: &[Directive]) -> Vec<Budget> {
+    let mut budgets = Vec::new();
+    for directive in directives {
+        if directive.keyword != "custom"
+            || directive.args.first().map(String::as_str) != Some("budget")
+        {
+            continue;
+        }
+        let args: Vec<&str> = directive.args.iter().map(String::as_str).collect();
+        match parse_budget(directive.date, &args) {
+            Ok(budget) => budgets.push(budget),
+            Err(msg) => log::warn!("{}:{}: {}", directive.file.display(), directive.line, msg),
+        }
+    }
+    budgets
+}
+
+// Budgets from the ledger and, if given, from a separate budget file.
+pub fn load_budgets(directives: &[Directive], budget_path: Option<&str>) -> Vec<Budget> {
+    let mut budgets = ledger_budgets(directives);
+    if let Some(path) = budget_path {
+        budgets.extend(load_budget_file(path));
+    }
+    budgets.sort_by_key(|budget| budget.date);
+    budgets
+}
+
+const YEARS: std::ops::RangeInclusive<i32> = 1..=9998;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportPeriod {
+    Year(i32),
+    Quarter(i32, u32),
+    Month(i32, u32),
+}
+
+impl ReportPeriod {
+    // Years stay within what `start` of the following period can represent.
+    pub fn parse(s: &str) -> Option<ReportPeriod> {
+        let mut parts = s.splitn(2, '-');
+        let year = parts.next()?.parse().ok()?;
+        if !YEARS.contains(&year) {
+            return None;
+        }
+        match parts.next() {
+            None => Some(ReportPeriod::Year(year)),
+            Some(q) if q.starts_with('Q') => match q[1..].parse() {
+                Ok(quarter) if (1..=4).contains(&quarter) => {
+                    Some(ReportPeriod::Quarter(year, quarter))
+                }
+                _ => None,
+            },
+            Some(m) => match m.parse() {
+                Ok(month) if (1..=12).contains(&month) => Some(ReportPeriod::Month(year, month)),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn current() -> ReportPeriod {
+        let today = Local::today().naive_local();
+        ReportPeriod::Month(today.year(), today.month())
+    }
+
+    pub fn start(&self) -> NaiveDate {
+        match *self {
+            ReportPeriod::Year(year) => NaiveDate::from_ymd_opt(year, 1, 1).unwrap(),
+            ReportPeriod::Quarter(year, quarter) => {
+                NaiveDate::from_ymd_opt(year, quarter * 3 - 2, 1).unwrap()
+            }
+            ReportPeriod::Month(year, month) => NaiveDate::from_ymd_opt(year, month, 1).unwrap(),
+        }
+    }
+
+    pub fn next(&self) -> ReportPeriod {
+        match *self {
+            ReportPeriod::Year(year) => ReportPeriod::Year(year + 1),
+            ReportPeriod::Quarter(year, 4) => ReportPeriod::Quarter(year + 1, 1),
+            ReportPeriod::Quarter(year, quarter) => ReportPeriod::Quarter(year, quarter + 1),
+            ReportPeriod::Month(year, 12) => ReportPeriod::Month(year + 1, 1),
+            ReportPeriod::Month(year, month) => ReportPeriod::Month(year, month + 1),
+        }
+    }
+
+    pub fn previous(&self) -> ReportPeriod {
+        match *self {
+            ReportPeriod::Year(year) => ReportPeriod::Year(year - 1),
+            ReportPeriod::Quarter(year, 1) => ReportPeriod::Quarter(year - 1, 4),
+            ReportPeriod::Quarter(year, quarter) => ReportPeriod::Quarter(year, quarter - 1),
+            ReportPeriod::Month(year, 1) => ReportPeriod::Month(year - 1, 12),
+            ReportPeriod::Month(year, month) => ReportPeriod::Month(year, month - 1),
+        }
+    }
+}
+
+impl fmt::Display for ReportPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReportPeriod::Year(year) => write!(f, "{}", year),
+            ReportPeriod::Quarter(year, quarter) => write!(f, "{}-Q{}", year, quarter),
+            ReportPeriod::Month(year, month) => write!(f, "{}-{:02}", year, month),
+        }
+    }
+}
+
+pub fn budget_report(ledger: &Ledger, budgets: &[Budget], period: ReportPeriod) -> BudgetReport {
+    let start = period.start();
+    let end = period.next().start();
+    let mut budgeted: HashMap<(&str, &str), Decimal> = HashMap::new();
+    let mut date = start;
+    while date < end {
+        let mut active: HashMap<(&str, &str), &Budget> = HashMap::new();
+        for budget in budgets.iter().take_while(|budget| budget.date <= date) {
+            active.insert((&budget.account, &budget.currency), budget);
+        }
+        for (key, budget) in active {
+            *budgeted.entry(key).or_default() +=
+                budget.number / Decimal::from(budget.interval.days_containing(date));
+        }
+        date += Duration::days(1);
+    }
+    let mut rows: Vec<BudgetRow> = budgeted
+        .into_iter()
+        .map(|((account, currency), budgeted)| {
+            let mut actual = Decimal::default();
+            for txn in ledger.txns() {
+                if txn.flag() == TxnFlag::Balance || txn.date() < start || txn.date() >= end {
+                    continue;
+                }
+                for posting in txn.postings() {
+                    if posting.amount.currency.as_str() == currency
+                        && is_sub_account(&posting.account, account)
+                    {
+                        actual += posting.amount.number;
+                    }
+                }
+            }
+            let budgeted = budgeted.round_dp(2);
+            let percent_used = if budgeted.is_zero() {
+                None
+            } else {
+                Some((actual / budgeted * Decimal::new(100, 0)).round_dp(1))
+            };
+            BudgetRow {
+                account: account.to_string(),
+                currency: currency.to_string(),
+                budgeted,
+                actual,
+                remaining: budgeted - actual,
+                percent_used,
+            }
+        })
+        .collect();
+    rows.sort_by(|a, b| (&a.account, &a.currency).cmp(&(&b.account, &b.currency)));
+    BudgetReport {
+        period: period.to_string(),
+        start,
+        end: end - Duration::days(1),
+        previous: period.previous().to_string(),
+        next: period.next().to_string(),
+        rows,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_years_without_a_following_period() {
+        assert_eq!(ReportPeriod::parse("262143"), None);
+        assert_eq!(ReportPeriod::parse("9999-12"), None);
+        assert_eq!(ReportPeriod::parse("0"), None);
+        let last = ReportPeriod::parse("9998-Q4").unwrap();
+        assert_eq!(
+            last.next().start(),
+            NaiveDate::from_ymd_opt(9999, 1, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn parses_budget_entries() {
+        let budget =
+            parse_budget_line(r#"2021-01-01 custom "budget" Expenses:Food "monthly" 400.00 USD"#)
+                .unwrap();
+        assert_eq!(budget.account, "Expenses:Food");
+        assert_eq!(budget.interval, Interval::Monthly);
+        assert_eq!(budget.number, Decimal::new(40000, 2));
+        assert!(parse_budget_line(r#"2021-01-01 custom "goal" Expenses:Food 1 USD"#).is_err());
+    }
+}
@@ -0,0 +1,262 @@
+use chrono::NaiveDate;
+use lumi::{Ledger, Meta, Transaction};
+use lumi_server_defs::{BalanceChange, Changes, ModifiedTxn};
+use rust_decimal::Decimal;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+// Metadata as sorted `key: value` pairs, without the source locations.
+fn meta_str(meta: &Meta) -> String {
+    let mut pairs: Vec<String> = meta
+        .iter()
+        .map(|(key, (value, _))| format!("{}: {}", key, value))
+        .collect();
+    pairs.sort();
+    pairs.join(", ")
+}
+
+// Identifies a transaction by its content, ignoring where it is in the file,
+// so that moving entries around does not show up as a change.
+pub fn fingerprint(txn: &Transaction) -> String {
+    let mut tags: Vec<String> = txn.tags().iter().map(|tag| tag.to_string()).collect();
+    tags.sort();
+    let mut links: Vec<String> = txn.links().iter().map(|link| link.to_string()).collect();
+    links.sort();
+    let mut s = format!(
+        "{} {:?} {:?} {:?} {:?} {:?} {{{}}}",
+        txn.date(),
+        txn.flag(),
+        txn.payee(),
+        txn.narration(),
+        tags,
+        links,
+        meta_str(txn.meta())
+    );
+    for posting in txn.postings() {
+        s.push_str(&format!(
+            "\n{} {} {:?} {:?} {{{}}}",
+            posting.account,
+            posting.amount,
+            posting.cost,
+            posting.price,
+            meta_str(&posting.meta)
+        ));
+    }
+    s
+}
+
+fn identity(txn: &Transaction) -> (NaiveDate, &str, &str) {
+    (txn.date(), txn.payee(), txn.narration())
+}
+
+// Where a transaction starts, to list transactions of the same day in file
+// order.
+fn position(txn: &Transaction) -> Option<(String, usize)> {
+    txn.postings()
+        .first()
+        .map(|posting| (posting.src.file.to_string(), posting.src.start.line))
+}
+
+// How many posting lines of their fingerprints two transactions share.
+fn shared_postings(a: &Transaction, b: &Transaction) -> usize {
+    let (a, b) = (fingerprint(a), fingerprint(b));
+    let b_postings: Vec<&str> = b.lines().skip(1).collect();
+    a.lines()
+        .skip(1)
+        .filter(|line| b_postings.contains(line))
+        .count()
+}
+
+fn account_units(ledger: &Ledger) -> HashMap<String, HashMap<String, Decimal>> {
+    ledger
+        .balance_sheet()
+        .iter()
+        .map(|(account, currencies)| {
+            let units = currencies
+                .iter()
+                .map(|(currency, lots)| (currency.to_string(), lots.values().sum()))
+                .filter(|(_, number): &(String, Decimal)| !number.is_zero())
+                .collect();
+            (account.to_string(), units)
+        })
+        .collect()
+}
+
+pub fn diff(old: &Ledger, new: &Ledger, generation: u64, loaded_at: i64) -> Changes<Transaction> {
+    let mut unmatched: HashMap<String, Vec<&Transaction>> = HashMap::new();
+    for txn in old.txns() {
+        unmatched.entry(fingerprint(txn)).or_default().push(txn);
+    }
+    let mut added = Vec::new();
+    for txn in new.txns() {
+        match unmatched.get_mut(&fingerprint(txn)).and_then(Vec::pop) {
+            Some(_) => {}
+            None => added.push(txn),
+        }
+    }
+    let mut removed: Vec<&Transaction> = unmatched.into_values().flatten().collect();
+    removed.sort_by_key(|txn| (txn.date(), position(txn)));
+
+    // An added and a removed transaction with the same date, payee and
+    // narration are reported as one modification. Of several such removed
+    // ones, the one with the most postings in common is paired, and the first
+    // in the file if that is a tie.
+    let mut modified = Vec::new();
+    added.retain(|after| {
+        let paired = removed
+            .iter()
+            .enumerate()
+            .filter(|(_, before)| identity(before) == identity(after))
+            .max_by_key(|(index, before)| (shared_postings(before, after), Reverse(*index)))
+            .map(|(index, _)| index);
+        match paired {
+            Some(index) => {
+                let before = removed.remove(index);
+                modified.push(ModifiedTxn {
+                    before: before.clone(),
+                    after: (*after).clone(),
+                });
+                false
+            }
+            None => true,
+        }
+    });
+
+    let (old_units, new_units) = (account_units(old), account_units(new));
+    let empty = HashMap::new();
+    let mut accounts: Vec<BalanceChange> = old_units
+        .keys()
+        .chain(
+            new_units
+                .keys()
+                .filter(|account| !old_units.contains_key(*account)),
+        )
+        .filter_map(|account| {
+            let before = old_units.get(account).unwrap_or(&empty);
+            let after = new_units.get(account).unwrap_or(&empty);
+            (before != after).then(|| BalanceChange {
+                account: account.clone(),
+                before: before.clone(),
+                after: after.clone(),
+            })
+        })
+        .collect();
+    accounts.sort_by(|a, b| a.account.cmp(&b.account));
+
+    Changes {
+        generation,
+        loaded_at,
+        added: added.into_iter().cloned().collect(),
+        removed: removed.into_iter().cloned().collect(),
+        modified,
+        accounts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff_files(name: &str, old: &str, new: &str) -> Changes<Transaction> {
+        let dir = std::env::temp_dir().join(format!(
+            "lumi-server-changes-{}-{}",
+            std::process::id(),
+            name
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.beancount");
+        let mut ledgers = [old, new].iter().map(|content| {
+            std::fs::write(&path, format!("{}{}", OPEN, content)).unwrap();
+            Ledger::from_file(path.to_str().unwrap()).0
+        });
+        let (old, new) = (ledgers.next().unwrap(), ledgers.next().unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+        diff(&old, &new, 2, 0)
+    }
+
+    const OPEN: &str = "2021-01-01 open Assets:Cash USD
+2021-01-01 open Expenses:Food USD
+2021-01-01 open Expenses:Coffee USD
+
+";
+
+    const LUNCH: &str = "2021-01-02 * \"Shop\" \"Lunch\"
+  Expenses:Food 10 USD
+  Assets:Cash
+";
+
+    const COFFEE: &str = "2021-01-03 * \"Cafe\" \"Coffee\"
+  Expenses:Coffee 3 USD
+  Assets:Cash
+";
+
+    fn amounts(txn: &Transaction) -> Vec<String> {
+        txn.postings()
+            .iter()
+            .map(|posting| format!("{} {}", posting.account, posting.amount))
+            .collect()
+    }
+
+    #[test]
+    fn moving_a_transaction_is_not_a_change() {
+        let old = format!("{}\n{}", LUNCH, COFFEE);
+        let new = format!("{}\n{}", COFFEE, LUNCH);
+        let changes = diff_files("move", &old, &new);
+        assert!(changes.added.is_empty());
+        assert!(changes.removed.is_empty());
+        assert!(changes.modified.is_empty());
+        assert!(changes.accounts.is_empty());
+    }
+
+    #[test]
+    fn pairs_edits_into_modifications() {
+        let old = format!("{}\n{}", LUNCH, COFFEE);
+        let new = format!(
+            "{}\n{}",
+            LUNCH.replace("10 USD", "12 USD"),
+            COFFEE.replace("2021-01-03", "2021-01-04")
+        );
+        let changes = diff_files("modify", &old, &new);
+        assert_eq!(changes.modified.len(), 1);
+        assert_eq!(changes.modified[0].before.narration(), "Lunch");
+        assert!(amounts(&changes.modified[0].after).contains(&"Expenses:Food 12 USD".to_string()));
+        // a new date is a different transaction
+        assert_eq!(changes.added.len(), 1);
+        assert_eq!(changes.removed.len(), 1);
+        assert_eq!(changes.accounts.len(), 2);
+    }
+
+    #[test]
+    fn detects_tag_and_meta_edits() {
+        let tagged = LUNCH.replace("\"Lunch\"", "\"Lunch\" #work");
+        let changes = diff_files("tag", LUNCH, &tagged);
+        assert_eq!(changes.modified.len(), 1);
+        assert!(changes.accounts.is_empty());
+
+        let with_meta = LUNCH.replace("\"Lunch\"\n", "\"Lunch\"\n  receipt: \"yes\"\n");
+        let changes = diff_files("meta", LUNCH, &with_meta);
+        assert_eq!(changes.modified.len(), 1);
+        assert!(changes.accounts.is_empty());
+    }
+
+    #[test]
+    fn pairs_identical_transactions_by_their_postings() {
+        let old = format!("{}\n{}", LUNCH, LUNCH.replace("10 USD", "20 USD"));
+        // The second lunch only gets a note and comes first now, while the
+        // first one changes its amount.
+        let new = format!(
+            "{}\n{}",
+            LUNCH
+                .replace("10 USD", "20 USD")
+                .replace("Assets:Cash\n", "Assets:Cash\n    note: \"cash\"\n"),
+            LUNCH.replace("10 USD", "11 USD"),
+        );
+        let changes = diff_files("ties", &old, &new);
+        assert_eq!(changes.modified.len(), 2);
+        for modified in &changes.modified {
+            let (before, after) = (amounts(&modified.before), amounts(&modified.after));
+            let food = |amounts: &[String]| amounts.contains(&"Expenses:Food 20 USD".to_string());
+            assert_eq!(food(&before), food(&after), "{:?} {:?}", before, after);
+        }
+    }
+}
@@ -0,0 +1,336 @@
+use crate::budget::{self, ReportPeriod};
+use crate::commodities::Commodities;
+use crate::directives;
+#[cfg(feature = "web-ui")]
+use crate::embedded::WEB_DIR;
+use crate::handlers;
+use crate::views;
+use clap::ArgMatches;
+#[cfg(feature = "web-ui")]
+use include_dir::Dir;
+use lumi::{ErrorLevel, Ledger, Transaction, TxnFlag};
+use lumi_server_defs::{
+    export_name, FilterOptions, ServerOptions, TrieOptions, DEFAULT_ENTRIES_PER_PAGE,
+};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::io;
+use std::path::Path;
+
+fn flag_str(flag: TxnFlag) -> &'static str {
+    match flag {
+        TxnFlag::Posted => "*",
+        TxnFlag::Balance => "bal",
+        TxnFlag::Pad => "pad",
+        TxnFlag::Pending => "!",
+    }
+}
+
+fn description(txn: &Transaction) -> String {
+    match (txn.payee().is_empty(), txn.narration().is_empty()) {
+        (false, false) => format!("{} {}", txn.payee(), txn.narration()),
+        (false, true) => txn.payee().to_string(),
+        _ => txn.narration().to_string(),
+    }
+}
+
+fn numbers_str(numbers: &HashMap<&str, Decimal>) -> String {
+    let mut entries: Vec<_> = numbers.iter().filter(|(_, n)| !n.is_zero()).collect();
+    entries.sort_by_key(|(c, _)| **c);
+    entries
+        .into_iter()
+        .map(|(c, n)| format!("{} {}", n, c))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn print_table(rows: &[Vec<String>], right_aligned: &[bool]) {
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| {
+            rows.iter()
+                .filter_map(|row| row.get(i))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for row in rows {
+        let cells: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| {
+                if right_aligned.get(i).copied().unwrap_or(false) {
+                    format!("{:>width$}", cell, width = widths[i])
+                } else {
+                    format!("{:<width$}", cell, width = widths[i])
+                }
+            })
+            .collect();
+        println!("{}", cells.join("  ").trim_end());
+    }
+}
+
+fn load(matches: &ArgMatches) -> (Ledger, Vec<lumi::Error>) {
+    Ledger::from_file(matches.value_of("INPUT").unwrap())
+}
+
+pub fn check(matches: &ArgMatches) -> bool {
+    let (_, errors) = load(matches);
+    if matches.is_present("JSON") {
+        println!("{}", serde_json::to_string_pretty(&errors).unwrap());
+    } else {
+        for error in errors.iter() {
+            let level = match error.level {
+                ErrorLevel::Error => "error",
+                ErrorLevel::Warning => "warning",
+                ErrorLevel::Info => "info",
+            };
+            println!(
+                "{}:{}:{}: {}: {}",
+                error.src.file, error.src.start.line, error.src.start.col, level, error.msg
+            );
+        }
+    }
+    !errors.iter().any(|e| e.level == ErrorLevel::Error)
+}
+
+pub fn balance(matches: &ArgMatches) -> bool {
+    let (ledger, _) = load(matches);
+    let root = matches.value_of("ROOT").unwrap();
+    let options = TrieOptions {
+        show_closed: Some(matches.is_present("SHOW_CLOSED")),
+    };
+    let table = match handlers::build_trie_table(&ledger, root, options) {
+        Some(table) => table,
+        None => {
+            eprintln!("Unknown root account {}", root);
+            return false;
+        }
+    };
+    if matches.is_present("JSON") {
+        println!("{}", serde_json::to_string_pretty(&table).unwrap());
+        return true;
+    }
+    let mut rows = vec![std::iter::once(String::new())
+        .chain(table.currencies.iter().map(|c| c.to_string()))
+        .collect::<Vec<_>>()];
+    for row in table.rows.iter() {
+        let name = format!("{}{}", "  ".repeat(row.level), row.name);
+        rows.push(std::iter::once(name).chain(row.numbers.clone()).collect());
+    }
+    let right_aligned: Vec<bool> = (0..=table.currencies.len()).map(|i| i > 0).collect();
+    print_table(&rows, &right_aligned);
+    true
+}
+
+pub fn journal(matches: &ArgMatches) -> bool {
+    let (ledger, _) = load(matches);
+    let account = matches.value_of("ACCOUNT");
+    // clap has already rejected values that are not positive numbers.
+    let options = FilterOptions {
+        entries: matches.value_of("ENTRIES").map(|n| n.parse().unwrap()),
+        page: matches.value_of("PAGE").map(|n| n.parse().unwrap()),
+        old_first: Some(matches.is_present("OLD_FIRST")),
+        time: matches.value_of("TIME").map(str::to_string),
+        show_lots: Some(matches.is_present("LOTS")),
+        ..Default::default()
+    };
+    let (items, total) = match handlers::build_journal(&ledger, account, &options, None) {
+        Ok(journal) => journal,
+        Err(e) => {
+            eprintln!("{}", e);
+            return false;
+        }
+    };
+    if matches.is_present("JSON") {
+        println!("{}", serde_json::to_string_pretty(&(items, total)).unwrap());
+        return true;
+    }
+    let mut rows = Vec::new();
+    for item in items.iter() {
+        let txn = item.txn;
+        let mut row = vec![
+            txn.date().to_string(),
+            flag_str(txn.flag()).to_string(),
+            description(txn),
+        ];
+        if account.is_some() {
+            row.push(numbers_str(&item.changes));
+            row.push(numbers_str(&item.balance));
+        }
+        rows.push(row);
+        if !matches.is_present("POSTINGS") {
+            continue;
+        }
+        for posting in txn.postings() {
+            rows.push(vec![
+                String::new(),
+                String::new(),
+                format!("  {}", posting.account),
+                posting.amount.to_string(),
+            ]);
+        }
+    }
+    print_table(&rows, &[false, false, false, true, true]);
+    println!("{} of {} transactions", items.len(), total);
+    true
+}
+
+#[cfg(feature = "web-ui")]
+const STATIC_MARKER: &str = r#"<meta name="lumi-static" content="true">"#;
+
+fn write_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, contents)
+}
+
+fn write_json<T: Serialize>(root: &Path, rel_path: &str, value: &T) -> io::Result<()> {
+    let contents = serde_json::to_vec(value).map_err(io::Error::from)?;
+    write_file(&root.join(rel_path), &contents)
+}
+
+#[cfg(feature = "web-ui")]
+fn write_assets(dir: &Dir, root: &Path) -> io::Result<()> {
+    for file in dir.files() {
+        write_file(&root.join(file.path()), file.contents())?;
+    }
+    for sub_dir in dir.dirs() {
+        write_assets(sub_dir, root)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "web-ui")]
+fn write_frontend(root: &Path) -> io::Result<()> {
+    write_assets(&WEB_DIR, root)?;
+    // The app routes on the fragment of this one page, and loads its assets
+    // next to it, so the export can be hosted under any path or opened from
+    // the file system. Trunk refers to its files as `/name`, in attributes
+    // and in the script that loads the wasm module.
+    let mut index =
+        String::from_utf8_lossy(WEB_DIR.get_file("index.html").unwrap().contents()).to_string();
+    for file in WEB_DIR.files() {
+        let name = file.path().display();
+        for quote in ['"', '\''] {
+            index = index.replace(
+                &format!("{}/{}{}", quote, name, quote),
+                &format!("{}./{}{}", quote, name, quote),
+            );
+        }
+    }
+    let index = index.replacen("</head>", &format!("{}</head>", STATIC_MARKER), 1);
+    write_file(&root.join("index.html"), index.as_bytes())
+}
+
+// Builds the journal once, with the running balances, and splits it into
+// the pages the API would return.
+fn write_journal_pages(
+    ledger: &Ledger,
+    root: &Path,
+    account: Option<&str>,
+    rel_dir: &str,
+) -> io::Result<()> {
+    let options = FilterOptions {
+        entries: Some(usize::MAX),
+        ..Default::default()
+    };
+    let (journal, total) = handlers::build_journal(ledger, account, &options, None)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    if journal.is_empty() {
+        return write_json(root, &format!("{}/page-1.json", rel_dir), &(journal, total));
+    }
+    for (index, page) in journal.chunks(DEFAULT_ENTRIES_PER_PAGE).enumerate() {
+        write_json(
+            root,
+            &format!("{}/page-{}.json", rel_dir, index + 1),
+            &(page, total),
+        )?;
+    }
+    Ok(())
+}
+
+fn export(
+    ledger: &Ledger,
+    errors: &[lumi::Error],
+    matches: &ArgMatches,
+    root: &Path,
+) -> io::Result<()> {
+    write_json(root, "api/options.json", &ServerOptions { read_only: true })?;
+    write_json(root, "api/errors.json", &errors)?;
+    write_json(
+        root,
+        "api/balances.json",
+        &handlers::balance_sheet_to_list(ledger.balance_sheet()),
+    )?;
+    let accounts = handlers::build_account_list(ledger);
+    write_json(root, "api/accounts.json", &accounts)?;
+    let directives = directives::scan(matches.value_of("INPUT").unwrap());
+    let budgets = budget::load_budgets(&directives, matches.value_of("BUDGET"));
+    let report = budget::budget_report(ledger, &budgets, ReportPeriod::current());
+    write_json(root, "api/budget.json", &report)?;
+    write_journal_pages(ledger, root, None, "api/journal")?;
+    let views = views::load(&views::views_path(matches.value_of("INPUT").unwrap()));
+    write_json(root, "api/views.json", &views)?;
+    let commodities = Commodities::load(ledger, &directives);
+    write_json(root, "api/commodities.json", &commodities.list())?;
+    for commodity in commodities.list() {
+        let details = commodities.details(ledger, &commodity.name);
+        write_json(
+            root,
+            &format!("api/commodity/{}.json", export_name(&commodity.name)),
+            &details,
+        )?;
+    }
+
+    let mut names = BTreeSet::new();
+    for account in accounts.iter() {
+        let parts: Vec<_> = account.name.split(':').collect();
+        for end in 1..=parts.len() {
+            names.insert(parts[..end].join(":"));
+        }
+    }
+    for name in names.iter() {
+        if !name.contains(':') {
+            let trie = handlers::build_trie_table(ledger, name, TrieOptions::default());
+            write_json(
+                root,
+                &format!("api/trie/{}.json", export_name(name)),
+                &trie.unwrap_or_default(),
+            )?;
+        }
+        let overview = handlers::build_account_overview(ledger, name).unwrap_or_default();
+        write_json(
+            root,
+            &format!("api/account_info/{}.json", export_name(name)),
+            &overview,
+        )?;
+        let rel_dir = format!("api/account/{}", export_name(name));
+        write_journal_pages(ledger, root, Some(name), &rel_dir)?;
+    }
+    #[cfg(feature = "web-ui")]
+    write_frontend(root)?;
+    Ok(())
+}
+
+pub fn export_static(matches: &ArgMatches) -> bool {
+    if cfg!(not(feature = "web-ui")) {
+        eprintln!("export-static needs the web UI, but this build has no web-ui feature");
+        return false;
+    }
+    let (ledger, errors) = load(matches);
+    let root = Path::new(matches.value_of("DIR").unwrap());
+    match export(&ledger, &errors, matches, root) {
+        Ok(()) => {
+            println!("Exported to {}", root.display());
+            true
+        }
+        Err(e) => {
+            eprintln!("Cannot export to {}: {}", root.display(), e);
+            false
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use crate::directives::Directive;
+use crate::handlers::is_sub_account;
+use lumi::Ledger;
+use lumi_server_defs::{Commodity, CommodityDetails, CommodityHolding, Price};
+use rust_decimal::Decimal;
+use std::collections::{BTreeSet, HashMap};
+
+// Balances of these accounts count as held.
+const HOLDING_ROOTS: [&str; 2] = ["Assets", "Liabilities"];
+
+fn is_held(account: &str) -> bool {
+    HOLDING_ROOTS
+        .iter()
+        .any(|root| is_sub_account(account, root))
+}
+
+// Prices from `price` entries and from the prices and costs of postings,
+// oldest first.
+fn price_history(ledger: &Ledger, directives: &[Directive]) -> HashMap<String, Vec<Price>> {
+    let mut prices: HashMap<String, Vec<Price>> = HashMap::new();
+    for directive in directives.iter().filter(|d| d.keyword == "price") {
+        let price = match directive.args.as_slice() {
+            [name, number, currency, ..] => number
+                .parse::<Decimal>()
+                .map(|number| {
+                    let price = Price {
+                        date: directive.date,
+                        number,
+                        currency: currency.clone(),
+                    };
+                    (name.clone(), price)
+                })
+                .map_err(|e| format!("invalid price {}: {}", number, e)),
+            _ => Err("price needs a commodity and an amount".to_string()),
+        };
+        match price {
+            Ok((name, price)) => prices.entry(name).or_default().push(price),
+            Err(msg) => log::warn!("{}:{}: {}", directive.file.display(), directive.line, msg),
+        }
+    }
+    for txn in ledger.txns() {
+        for posting in txn.postings() {
+            let unit_price = posting
+                .price
+                .as_ref()
+                .or_else(|| posting.cost.as_ref().map(|cost| &cost.amount));
+            if let Some(unit_price) = unit_price {
+                prices
+                    .entry(posting.amount.currency.to_string())
+                    .or_default()
+                    .push(Price {
+                        date: txn.date(),
+                        number: unit_price.number,
+                        currency: unit_price.currency.to_string(),
+                    });
+            }
+        }
+    }
+    for history in prices.values_mut() {
+        history.sort_by_key(|price| price.date);
+    }
+    prices
+}
+
+// Prices are often more precise than the amounts they price, so only the
+// amounts of postings count.
+fn precisions(ledger: &Ledger) -> HashMap<String, u32> {
+    let mut precisions: HashMap<String, u32> = HashMap::new();
+    for txn in ledger.txns() {
+        for posting in txn.postings() {
+            let precision = precisions
+                .entry(posting.amount.currency.to_string())
+                .or_default();
+            *precision = (*precision).max(posting.amount.number.scale());
+        }
+    }
+    precisions
+}
+
+fn held_units(ledger: &Ledger) -> HashMap<String, Decimal> {
+    let mut units: HashMap<String, Decimal> = HashMap::new();
+    for (account, account_map) in ledger.balance_sheet() {
+        if !is_held(account) {
+            continue;
+        }
+        for (currency, lots) in account_map {
+            *units.entry(currency.to_string()).or_default() += lots.values().sum::<Decimal>();
+        }
+    }
+    units
+}
+
+// Every commodity with a `commodity` entry, a price, or a balance.
+fn names(
+    ledger: &Ledger,
+    directives: &[Directive],
+    prices: &HashMap<String, Vec<Price>>,
+) -> BTreeSet<String> {
+    let mut names: BTreeSet<String> = directives
+        .iter()
+        .filter(|d| d.keyword == "commodity")
+        .filter_map(|d| d.args.first().cloned())
+        .collect();
+    for info in ledger.accounts().values() {
+        names.extend(info.currencies().iter().map(|c| c.to_string()));
+    }
+    for account_map in ledger.balance_sheet().values() {
+        names.extend(account_map.keys().map(|c| c.to_string()));
+    }
+    for (name, history) in prices {
+        names.insert(name.clone());
+        names.extend(history.iter().map(|price| price.currency.clone()));
+    }
+    names
+}
+
+fn commodity_meta(directives: &[Directive], name: &str) -> Vec<(String, String)> {
+    directives
+        .iter()
+        .filter(|d| d.keyword == "commodity" && d.args.first().map(String::as_str) == Some(name))
+        .flat_map(|d| d.meta.iter().cloned())
+        .collect()
+}
+
+fn commodity(
+    name: String,
+    directives: &[Directive],
+    prices: &HashMap<String, Vec<Price>>,
+    precisions: &HashMap<String, u32>,
+    units: &HashMap<String, Decimal>,
+) -> Commodity {
+    Commodity {
+        meta: commodity_meta(directives, &name),
+        precision: precisions.get(&name).copied().unwrap_or(0),
+        units: units.get(&name).copied().unwrap_or_default(),
+        latest_price: prices
+            .get(&name)
+            .and_then(|history| history.last().cloned()),
+        name,
+    }
+}
+
+// Computed once per snapshot, as every request would otherwise walk the
+// whole ledger.
+pub struct Commodities {
+    list: Vec<Commodity>,
+    prices: HashMap<String, Vec<Price>>,
+}
+
+impl Commodities {
+    pub fn load(ledger: &Ledger, directives: &[Directive]) -> Commodities {
+        let prices = price_history(ledger, directives);
+        let precisions = precisions(ledger);
+        let units = held_units(ledger);
+        let list = names(ledger, directives, &prices)
+            .into_iter()
+            .map(|name| commodity(name, directives, &prices, &precisions, &units))
+            .collect();
+        Commodities { list, prices }
+    }
+
+    pub fn list(&self) -> &[Commodity] {
+        &self.list
+    }
+
+    pub fn details(&self, ledger: &Ledger, name: &str) -> Option<CommodityDetails> {
+        let commodity = self.list.iter().find(|c| c.name == name)?.clone();
+        let holdings = holdings(ledger, name, commodity.latest_price.as_ref());
+        Some(CommodityDetails {
+            commodity,
+            prices: self.prices.get(name).cloned().unwrap_or_default(),
+            holdings,
+        })
+    }
+}
+
+fn holdings(ledger: &Ledger, name: &str, latest_price: Option<&Price>) -> Vec<CommodityHolding> {
+    let mut holdings = Vec::new();
+    for (account, account_map) in ledger.balance_sheet() {
+        if !is_held(account) {
+            continue;
+        }
+        for (currency, lots) in account_map {
+            if currency.as_str() != name {
+                continue;
+            }
+            let mut units = Decimal::ZERO;
+            let mut book_value: HashMap<String, Decimal> = HashMap::new();
+            for (cost, number) in lots {
+                units += number;
+                match cost {
+                    Some(unit_cost) => {
+                        *book_value
+                            .entry(unit_cost.amount.currency.to_string())
+                            .or_default() += unit_cost.amount.number * number
+                    }
+                    None => *book_value.entry(name.to_string()).or_default() += number,
+                }
+            }
+            if units.is_zero() {
+                continue;
+            }
+            let market_value = latest_price
+                .map(|price| (price.currency.clone(), price.number * units))
+                .into_iter()
+                .collect();
+            holdings.push(CommodityHolding {
+                account: account.to_string(),
+                units,
+                book_value,
+                market_value,
+            });
+        }
+    }
+    holdings.sort_by(|a, b| a.account.cmp(&b.account));
+    holdings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::directives;
+    use chrono::NaiveDate;
+
+    const LEDGER: &str = r#"2021-01-01 open Assets:Cash USD
+2021-01-01 open Assets:Broker
+2021-01-01 open Income:Gains USD
+2021-01-01 commodity STOCK
+  name: "Some Stock"
+
+2021-01-02 * "Buy"
+  Assets:Broker 10 STOCK {10.125 USD}
+  Assets:Cash -101.25 USD
+
+2021-01-03 price STOCK 11.5 USD
+
+2021-01-04 * "Sell"
+  Assets:Broker -2 STOCK {10.125 USD} @ 12 USD
+  Assets:Cash 24.00 USD
+  Income:Gains -3.75 USD
+"#;
+
+    fn load(name: &str) -> (Ledger, Commodities) {
+        let dir = std::env::temp_dir().join(format!(
+            "lumi-server-commodities-{}-{}",
+            std::process::id(),
+            name
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.beancount");
+        std::fs::write(&path, LEDGER).unwrap();
+        let path = path.to_str().unwrap();
+        let (ledger, _) = Ledger::from_file(path);
+        let commodities = Commodities::load(&ledger, &directives::scan(path));
+        std::fs::remove_dir_all(&dir).unwrap();
+        (ledger, commodities)
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2021, 1, day).unwrap()
+    }
+
+    fn usd(number: &str) -> Price {
+        Price {
+            date: date(1),
+            number: number.parse().unwrap(),
+            currency: "USD".to_string(),
+        }
+    }
+
+    #[test]
+    fn takes_precision_from_posting_amounts_only() {
+        let (_, commodities) = load("precision");
+        let precision = |name: &str| {
+            commodities
+                .list()
+                .iter()
+                .find(|c| c.name == name)
+                .unwrap()
+                .precision
+        };
+        assert_eq!(precision("USD"), 2);
+        assert_eq!(precision("STOCK"), 0);
+    }
+
+    #[test]
+    fn combines_price_entries_with_posting_prices_and_costs() {
+        let (ledger, commodities) = load("prices");
+        let details = commodities.details(&ledger, "STOCK").unwrap();
+        let prices = [(2, "10.125"), (3, "11.5"), (4, "12")]
+            .iter()
+            .map(|(day, number)| Price {
+                date: date(*day),
+                ..usd(number)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(details.prices, prices);
+        assert_eq!(details.commodity.latest_price, prices.last().cloned());
+        assert_eq!(
+            details.commodity.meta,
+            vec![("name".to_string(), "Some Stock".to_string())]
+        );
+        assert_eq!(details.holdings.len(), 1);
+        let holding = &details.holdings[0];
+        assert_eq!(holding.account, "Assets:Broker");
+        assert_eq!(holding.units, Decimal::from(8));
+        assert_eq!(holding.book_value["USD"], "81.000".parse().unwrap());
+        assert_eq!(holding.market_value["USD"], Decimal::from(96));
+    }
+}
@@ -0,0 +1,212 @@
+use lumi::Ledger;
+use lumi_server_defs::{CompleteOptions, Completion, CompletionKind};
+use std::collections::HashMap;
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 50;
+const PAYEE_ACCOUNTS: usize = 5;
+
+// Lower is better: prefix, prefix of a segment like `Food` in
+// `Expenses:Food`, substring, then the query's characters in order.
+fn match_rank(candidate: &str, query: &str) -> Option<u8> {
+    if query.is_empty() {
+        return Some(0);
+    }
+    let candidate = candidate.to_lowercase();
+    if candidate.starts_with(query) {
+        return Some(0);
+    }
+    if candidate
+        .match_indices(query)
+        .any(|(i, _)| candidate[..i].ends_with(|c: char| c == ':' || c == ' ' || c == '-'))
+    {
+        return Some(1);
+    }
+    if candidate.contains(query) {
+        return Some(2);
+    }
+    let mut chars = candidate.chars();
+    if query.chars().all(|q| chars.any(|c| c == q)) {
+        return Some(3);
+    }
+    None
+}
+
+fn count(counts: &mut HashMap<String, usize>, value: impl Into<String> + AsRef<str>) {
+    if value.as_ref().is_empty() {
+        return;
+    }
+    *counts.entry(value.into()).or_default() += 1;
+}
+
+fn frequencies(ledger: &Ledger, kind: CompletionKind) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    match kind {
+        CompletionKind::Account => {
+            for account in ledger.accounts().keys() {
+                counts.entry(account.to_string()).or_default();
+            }
+        }
+        CompletionKind::Currency => {
+            for info in ledger.accounts().values() {
+                for currency in info.currencies() {
+                    counts.entry(currency.to_string()).or_default();
+                }
+            }
+        }
+        _ => {}
+    }
+    for txn in ledger.txns() {
+        match kind {
+            CompletionKind::Account => {
+                for posting in txn.postings() {
+                    count(&mut counts, posting.account.to_string());
+                }
+            }
+            CompletionKind::Currency => {
+                for posting in txn.postings() {
+                    count(&mut counts, posting.amount.currency.to_string());
+                }
+            }
+            CompletionKind::Payee => count(&mut counts, txn.payee()),
+            CompletionKind::Narration => count(&mut counts, txn.narration()),
+            CompletionKind::Tag => {
+                for tag in txn.tags() {
+                    count(&mut counts, tag.to_string());
+                }
+            }
+            CompletionKind::Link => {
+                for link in txn.links() {
+                    count(&mut counts, link.to_string());
+                }
+            }
+        }
+    }
+    counts
+}
+
+// The accounts posted to by the transactions of each payee, most used
+// first, in one pass over the ledger.
+fn payee_accounts(ledger: &Ledger) -> HashMap<&str, Vec<String>> {
+    let mut counts: HashMap<&str, HashMap<&str, usize>> = HashMap::new();
+    for txn in ledger.txns() {
+        let accounts = counts.entry(txn.payee()).or_default();
+        for posting in txn.postings() {
+            *accounts.entry(&posting.account).or_default() += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .map(|(payee, counts)| {
+            let mut accounts: Vec<_> = counts.into_iter().collect();
+            accounts.sort_by(|(a, m), (b, n)| n.cmp(m).then(a.cmp(b)));
+            let accounts = accounts
+                .into_iter()
+                .take(PAYEE_ACCOUNTS)
+                .map(|(account, _)| account.to_string())
+                .collect();
+            (payee, accounts)
+        })
+        .collect()
+}
+
+pub fn complete(ledger: &Ledger, options: &CompleteOptions) -> Vec<Completion> {
+    let query = options.prefix.trim().to_lowercase();
+    let mut candidates: Vec<(u8, String, usize)> = frequencies(ledger, options.kind)
+        .into_iter()
+        .filter_map(|(value, count)| match_rank(&value, &query).map(|rank| (rank, value, count)))
+        .collect();
+    candidates.sort_by(|(r1, v1, c1), (r2, v2, c2)| r1.cmp(r2).then(c2.cmp(c1)).then(v1.cmp(v2)));
+    let mut accounts = if options.kind == CompletionKind::Payee {
+        payee_accounts(ledger)
+    } else {
+        HashMap::new()
+    };
+    candidates
+        .into_iter()
+        .take(options.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT))
+        .map(|(_, value, count)| Completion {
+            accounts: accounts.remove(value.as_str()).unwrap_or_default(),
+            value,
+            count,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ledger(name: &str, payees: &[&str]) -> Ledger {
+        let dir = std::env::temp_dir().join(format!(
+            "lumi-server-complete-{}-{}",
+            std::process::id(),
+            name
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.beancount");
+        let mut content =
+            "2021-01-01 open Assets:Cash USD\n2021-01-01 open Expenses:Food USD\n".to_string();
+        for payee in payees {
+            content.push_str(&format!(
+                "2021-01-02 * \"{}\" \"\"\n  Expenses:Food 1 USD\n  Assets:Cash\n",
+                payee
+            ));
+        }
+        std::fs::write(&path, content).unwrap();
+        let (ledger, _) = Ledger::from_file(path.to_str().unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+        ledger
+    }
+
+    fn payees(ledger: &Ledger, prefix: &str, limit: Option<usize>) -> Vec<(String, usize)> {
+        let options = CompleteOptions {
+            kind: CompletionKind::Payee,
+            prefix: prefix.to_string(),
+            limit,
+        };
+        complete(ledger, &options)
+            .into_iter()
+            .map(|completion| (completion.value, completion.count))
+            .collect()
+    }
+
+    #[test]
+    fn ranks_prefixes_segments_substrings_then_subsequences() {
+        let ledger = ledger(
+            "rank",
+            &[
+                "Farm Of Old Days",
+                "Seafood",
+                "Seafood",
+                "Big Food",
+                "Foodstore",
+                "Food Court",
+                "Food Court",
+                "Bar",
+            ],
+        );
+        let expected = [
+            ("Food Court", 2),
+            ("Foodstore", 1),
+            ("Big Food", 1),
+            ("Seafood", 2),
+            ("Farm Of Old Days", 1),
+        ];
+        let expected: Vec<(String, usize)> = expected
+            .iter()
+            .map(|(value, count)| (value.to_string(), *count))
+            .collect();
+        assert_eq!(payees(&ledger, " Food ", None), expected);
+    }
+
+    #[test]
+    fn caps_the_limit() {
+        let names: Vec<String> = (0..60).map(|i| format!("Payee {}", i)).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let ledger = ledger("limit", &names);
+        assert_eq!(payees(&ledger, "", None).len(), DEFAULT_LIMIT);
+        assert_eq!(payees(&ledger, "", Some(3)).len(), 3);
+        assert_eq!(payees(&ledger, "", Some(1000)).len(), MAX_LIMIT);
+    }
+}
@@ -0,0 +1,156 @@
+use std::convert::Infallible;
+use std::io::Write;
+use warp::http::header::{HeaderValue, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, VARY};
+use warp::http::StatusCode;
+use warp::reply::Response;
+
+const MIN_COMPRESS_SIZE: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+    Identity,
+}
+
+impl Encoding {
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+            Encoding::Identity => "identity",
+        }
+    }
+
+    #[cfg(feature = "web-ui")]
+    pub fn extension(self) -> Option<&'static str> {
+        match self {
+            Encoding::Brotli => Some(".br"),
+            Encoding::Gzip => Some(".gz"),
+            Encoding::Identity => None,
+        }
+    }
+}
+
+// The encoding with the highest quality. `q=0` means "not acceptable"
+// (RFC 9110, section 12.5.3), and `*` stands for the encodings not listed.
+pub fn preferred(accept_encoding: Option<&str>) -> Encoding {
+    let mut qualities: Vec<(&str, f32)> = Vec::new();
+    for item in accept_encoding.unwrap_or_default().split(',') {
+        let mut parts = item.split(';');
+        let name = parts.next().unwrap_or_default().trim();
+        let quality = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.parse::<f32>().ok())
+            .unwrap_or(1.0);
+        qualities.push((name, quality));
+    }
+    let quality_of = |name: &str| {
+        qualities
+            .iter()
+            .find(|(n, _)| *n == name)
+            .or_else(|| qualities.iter().find(|(n, _)| *n == "*"))
+            .map_or(0.0, |(_, q)| *q)
+    };
+    let mut best = (Encoding::Identity, 0.0);
+    // Brotli first, so that it wins ties.
+    for encoding in [Encoding::Brotli, Encoding::Gzip] {
+        let quality = quality_of(encoding.name());
+        if quality > best.1 {
+            best = (encoding, quality);
+        }
+    }
+    best.0
+}
+
+pub fn compress(encoding: Encoding, data: &[u8]) -> Vec<u8> {
+    match encoding {
+        Encoding::Brotli => {
+            let mut buffer = Vec::new();
+            {
+                let mut writer = brotli::CompressorWriter::new(&mut buffer, 4096, 5, 22);
+                writer.write_all(data).unwrap();
+            }
+            buffer
+        }
+        Encoding::Gzip => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        }
+        Encoding::Identity => data.to_vec(),
+    }
+}
+
+// Documents are streamed and mostly compressed already, so only JSON and text
+// responses are buffered and compressed.
+fn is_text(response: &Response) -> bool {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map_or(true, |value| {
+            value.starts_with("application/json") || value.starts_with("text/")
+        })
+}
+
+pub async fn compress_reply(
+    reply: impl warp::Reply,
+    accept_encoding: Option<String>,
+) -> Result<Response, Infallible> {
+    let response = reply.into_response();
+    let encoding = preferred(accept_encoding.as_deref());
+    if encoding == Encoding::Identity
+        || response.headers().contains_key(CONTENT_ENCODING)
+        || !is_text(&response)
+    {
+        return Ok(response);
+    }
+    let (mut parts, body) = response.into_parts();
+    let bytes = match warp::hyper::body::to_bytes(body).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            log::error!("Cannot read response body: {}", e);
+            let mut resp = Response::default();
+            *resp.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+            return Ok(resp);
+        }
+    };
+    parts
+        .headers
+        .insert(VARY, HeaderValue::from_static("accept-encoding"));
+    if bytes.len() < MIN_COMPRESS_SIZE {
+        return Ok(Response::from_parts(parts, bytes.into()));
+    }
+    parts.headers.remove(CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(CONTENT_ENCODING, HeaderValue::from_static(encoding.name()));
+    Ok(Response::from_parts(
+        parts,
+        compress(encoding, &bytes).into(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn never_picks_refused_encodings() {
+        assert_eq!(preferred(Some("br;q=0")), Encoding::Identity);
+        assert_eq!(preferred(Some("*;q=0")), Encoding::Identity);
+        assert_eq!(preferred(Some("br;q=0, *")), Encoding::Gzip);
+        assert_eq!(preferred(Some("gzip, *;q=0")), Encoding::Gzip);
+    }
+
+    #[test]
+    fn picks_the_highest_quality() {
+        assert_eq!(preferred(Some("gzip;q=0.5, br;q=0.4")), Encoding::Gzip);
+        assert_eq!(preferred(Some("gzip, deflate, br")), Encoding::Brotli);
+        assert_eq!(preferred(Some("*")), Encoding::Brotli);
+        assert_eq!(preferred(Some("deflate")), Encoding::Identity);
+        assert_eq!(preferred(None), Encoding::Identity);
+    }
+}
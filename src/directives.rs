@@ -0,0 +1,207 @@
+use chrono::NaiveDate;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+// A dated line like `2021-01-02 document Assets:Cash "receipt.pdf"`. lumi's
+// `Ledger` only exposes accounts, transactions and balances, so entries such
+// as documents, prices, commodities and custom ones are read from the source
+// files here. This is a line scanner, not a parser: it follows `include`
+// lines with a literal path relative to the including file, but not glob
+// patterns, strings that span several lines or `pushtag`/`pushmeta` blocks,
+// and it reads directives that lumi reports as errors all the same.
+#[derive(Debug, Clone)]
+pub struct Directive {
+    pub date: NaiveDate,
+    pub keyword: String,
+    // the remaining tokens, with quotes removed
+    pub args: Vec<String>,
+    // `key: value` lines right below the directive
+    pub meta: Vec<(String, String)>,
+    pub file: PathBuf,
+    pub line: usize,
+}
+
+// Splits a line at whitespace, keeping quoted strings together and dropping
+// a trailing `;` comment.
+pub fn tokens(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == ';' {
+            break;
+        } else if c == '"' {
+            chars.next();
+            let mut token = String::new();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => token.extend(chars.next()),
+                    c => token.push(c),
+                }
+            }
+            tokens.push(token);
+        } else {
+            let mut token = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                token.push(c);
+                chars.next();
+            }
+            tokens.push(token);
+        }
+    }
+    tokens
+}
+
+// `  name: "US Dollar"`; postings like `  Assets:Cash 10 USD` are not
+// metadata.
+pub fn meta_line(line: &str) -> Option<(String, String)> {
+    let (key, value) = line.trim().split_once(':')?;
+    if !key.starts_with(|c: char| c.is_ascii_lowercase())
+        || !key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return None;
+    }
+    Some((key.to_string(), tokens(value).join(" ")))
+}
+
+fn scan_file(path: &Path, visited: &mut HashSet<PathBuf>, directives: &mut Vec<Directive>) {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if !visited.insert(path.clone()) {
+        return;
+    }
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) => {
+            log::warn!("Cannot read {}: {}", path.display(), e);
+            return;
+        }
+    };
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    // whether indented lines still belong to the last directive's metadata
+    let mut in_meta = false;
+    for (index, line) in content.lines().enumerate() {
+        // postings and metadata are indented
+        if line.starts_with(char::is_whitespace) {
+            in_meta = in_meta && !line.trim().is_empty();
+            if in_meta {
+                match (meta_line(line), directives.last_mut()) {
+                    (Some(pair), Some(directive)) => directive.meta.push(pair),
+                    _ => in_meta = false,
+                }
+            }
+            continue;
+        }
+        in_meta = false;
+        let mut tokens = tokens(line);
+        if tokens.len() == 2 && tokens[0] == "include" {
+            scan_file(&dir.join(&tokens[1]), visited, directives);
+            continue;
+        }
+        if tokens.len() < 2 {
+            continue;
+        }
+        let date = match NaiveDate::parse_from_str(&tokens[0], "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => continue,
+        };
+        let args = tokens.split_off(2);
+        in_meta = true;
+        directives.push(Directive {
+            date,
+            keyword: tokens.pop().unwrap(),
+            args,
+            meta: Vec::new(),
+            file: path.clone(),
+            line: index + 1,
+        });
+    }
+}
+
+// Reads the directives of a ledger file and the files it includes.
+pub fn scan(path: &str) -> Vec<Directive> {
+    let mut directives = Vec::new();
+    scan_file(Path::new(path), &mut HashSet::new(), &mut directives);
+    directives.sort_by_key(|directive| directive.date);
+    directives
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan_files(name: &str, files: &[(&str, &str)]) -> Vec<Directive> {
+        let dir = std::env::temp_dir().join(format!(
+            "lumi-server-directives-{}-{}",
+            std::process::id(),
+            name
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (file, content) in files {
+            std::fs::write(dir.join(file), content).unwrap();
+        }
+        let directives = scan(dir.join(files[0].0).to_str().unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+        directives
+    }
+
+    #[test]
+    fn keeps_quoted_semicolons_and_escaped_quotes() {
+        assert_eq!(
+            tokens(r#"2021-01-02 custom "note" "a; b" "say \"hi\"" ; comment"#),
+            vec!["2021-01-02", "custom", "note", "a; b", r#"say "hi""#]
+        );
+    }
+
+    #[test]
+    fn reads_metadata_right_below_a_directive() {
+        let directives = scan_files(
+            "meta",
+            &[(
+                "main.beancount",
+                "2021-01-01 commodity USD\r\n  name: \"US Dollar\"\r\n  precision: 2\r\n\r\n  note: \"not meta\"\r\n2021-01-02 * \"Shop\"\r\n  Assets:Cash -1 USD\r\n  memo: \"posting meta\"\r\n",
+            )],
+        );
+        assert_eq!(directives.len(), 2);
+        assert_eq!(directives[0].keyword, "commodity");
+        assert_eq!(directives[0].args, vec!["USD"]);
+        assert_eq!(
+            directives[0].meta,
+            vec![
+                ("name".to_string(), "US Dollar".to_string()),
+                ("precision".to_string(), "2".to_string()),
+            ]
+        );
+        assert_eq!(directives[1].keyword, "*");
+        assert_eq!(directives[1].line, 6);
+        assert!(directives[1].meta.is_empty());
+    }
+
+    #[test]
+    fn follows_includes_once() {
+        let directives = scan_files(
+            "include",
+            &[
+                (
+                    "main.beancount",
+                    "include \"prices.beancount\"\n2021-01-03 document Assets:Cash \"a.pdf\"\n",
+                ),
+                (
+                    "prices.beancount",
+                    "include \"main.beancount\"\n2021-01-02 price EUR 1.2 USD\n",
+                ),
+            ],
+        );
+        let keywords: Vec<&str> = directives.iter().map(|d| d.keyword.as_str()).collect();
+        assert_eq!(keywords, vec!["price", "document"]);
+        assert!(directives[0].file.ends_with("prices.beancount"));
+        assert_eq!(directives[0].line, 2);
+        assert_eq!(directives[1].args, vec!["Assets:Cash", "a.pdf"]);
+    }
+}
@@ -0,0 +1,632 @@
+use crate::directives::{self, Directive};
+use crate::handlers::is_sub_account;
+use chrono::NaiveDate;
+use lumi::{Ledger, Meta};
+use lumi_server_defs::{Document, UploadOptions};
+use std::ffi::OsStr;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use warp::http::header::{
+    CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_TYPE, X_CONTENT_TYPE_OPTIONS,
+};
+use warp::hyper::body::{Body, Bytes};
+use warp::reply::Response;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+pub const MAX_UPLOAD_SIZE: u64 = 20 * 1024 * 1024;
+
+// (extension, content type, first bytes of the file)
+const UPLOAD_TYPES: [(&str, &str, &[u8]); 5] = [
+    ("pdf", "application/pdf", b"%PDF-"),
+    ("png", "image/png", b"\x89PNG\r\n\x1a\n"),
+    ("jpg", "image/jpeg", b"\xff\xd8\xff"),
+    ("jpeg", "image/jpeg", b"\xff\xd8\xff"),
+    ("gif", "image/gif", b"GIF8"),
+];
+
+// The directories documents may be served from. Relative paths in the
+// ledger are looked up in each root in turn.
+pub struct Documents {
+    roots: Vec<PathBuf>,
+}
+
+impl Documents {
+    // Without --documents, the directory of the ledger is the only root.
+    pub fn new(ledger_path: &str, roots: &[&str]) -> Documents {
+        let roots: Vec<PathBuf> = if roots.is_empty() {
+            let dir = Path::new(ledger_path)
+                .parent()
+                .unwrap_or_else(|| Path::new(""));
+            vec![if dir.as_os_str().is_empty() {
+                PathBuf::from(".")
+            } else {
+                dir.to_path_buf()
+            }]
+        } else {
+            roots.iter().map(PathBuf::from).collect()
+        };
+        let roots = roots
+            .into_iter()
+            .filter_map(|root| match root.canonicalize() {
+                Ok(root) => Some(root),
+                Err(e) => {
+                    log::warn!("Ignoring document root {}: {}", root.display(), e);
+                    None
+                }
+            })
+            .collect();
+        Documents { roots }
+    }
+
+    // Only paths listed in `referenced`, i.e. by `list`, are served; others
+    // fail with `NotFound`. Fails with `PermissionDenied` for paths outside of
+    // every root, including paths that leave a root through `..` or a
+    // symbolic link.
+    pub fn resolve(&self, path: &str, referenced: &[Document]) -> io::Result<PathBuf> {
+        if !referenced.iter().any(|document| document.path == path) {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "not a document"));
+        }
+        let path = Path::new(path);
+        let denied = || io::Error::new(io::ErrorKind::PermissionDenied, "outside document roots");
+        if path.components().any(|c| c == Component::ParentDir) {
+            return Err(denied());
+        }
+        let candidates: Vec<PathBuf> = if path.is_absolute() {
+            if !self.roots.iter().any(|root| path.starts_with(root)) {
+                return Err(denied());
+            }
+            vec![path.to_path_buf()]
+        } else {
+            self.roots.iter().map(|root| root.join(path)).collect()
+        };
+        let mut result = Err(io::Error::from(io::ErrorKind::NotFound));
+        for candidate in candidates {
+            match candidate.canonicalize() {
+                Ok(file) if self.roots.iter().any(|root| file.starts_with(root)) => {
+                    if file.is_file() {
+                        return Ok(file);
+                    }
+                }
+                Ok(_) => result = Err(denied()),
+                Err(_) => {}
+            }
+        }
+        result
+    }
+
+    // Uploads go to the first root. An existing file is never replaced.
+    pub async fn save_upload(&self, name: &str, data: &[u8]) -> io::Result<PathBuf> {
+        let root = self
+            .roots
+            .first()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no document root"))?;
+        let path = root.join(name);
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .await?;
+        if let Err(e) = file.write_all(data).await {
+            let _ = tokio::fs::remove_file(&path).await;
+            return Err(e);
+        }
+        Ok(path)
+    }
+}
+
+// `2021-01-02.Expenses.Food.receipt.pdf` for a receipt of Expenses:Food.
+pub fn upload_name(options: &UploadOptions) -> Result<String, String> {
+    let filename = options.filename.trim();
+    let is_plain_name = Path::new(filename).file_name() == Some(OsStr::new(filename))
+        && !filename.starts_with('.')
+        && !filename.contains(|c: char| c.is_control() || c == '"' || c == '\\');
+    if !is_plain_name {
+        return Err(format!("Invalid file name {}", options.filename));
+    }
+    Ok(format!(
+        "{}.{}.{}",
+        options.date.format("%Y-%m-%d"),
+        options.account.replace(':', "."),
+        filename
+    ))
+}
+
+// Accepts PDFs and images only. The extension, the declared content type and
+// the first bytes of the file have to agree.
+pub fn check_upload(filename: &str, content_type: Option<&str>, data: &[u8]) -> Result<(), String> {
+    if data.is_empty() {
+        return Err("The file is empty".to_string());
+    }
+    let extension = Path::new(filename)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let (_, expected_type, magic) = UPLOAD_TYPES
+        .iter()
+        .find(|(ext, _, _)| *ext == extension)
+        .ok_or_else(|| format!("Unsupported file type {}", filename))?;
+    if let Some(content_type) = content_type {
+        let content_type = content_type.split(';').next().unwrap_or_default().trim();
+        if content_type != *expected_type && content_type != "application/octet-stream" {
+            return Err(format!(
+                "Content type {} does not match {}",
+                content_type, filename
+            ));
+        }
+    }
+    if !data.starts_with(magic) {
+        return Err(format!(
+            "{} is not a valid {} file",
+            filename, expected_type
+        ));
+    }
+    Ok(())
+}
+
+// The path written to the ledger: just the file name for files next to the
+// ledger, since relative paths are resolved against the roots.
+pub fn entry_path(ledger_path: &str, file: &Path) -> String {
+    let ledger_dir = Path::new(ledger_path)
+        .canonicalize()
+        .ok()
+        .and_then(|path| path.parent().map(Path::to_path_buf));
+    match (ledger_dir, file.parent(), file.file_name()) {
+        (Some(ledger_dir), Some(dir), Some(name)) if ledger_dir == dir => {
+            name.to_string_lossy().to_string()
+        }
+        _ => file.display().to_string(),
+    }
+}
+
+pub async fn append_entry(
+    ledger_path: &str,
+    date: NaiveDate,
+    account: &str,
+    path: &str,
+) -> io::Result<()> {
+    let mut ledger = tokio::fs::OpenOptions::new()
+        .append(true)
+        .open(ledger_path)
+        .await?;
+    let entry = format!(
+        "\n{} document {} \"{}\"\n",
+        date.format("%Y-%m-%d"),
+        account,
+        path
+    );
+    ledger.write_all(entry.as_bytes()).await
+}
+
+// A transaction as the ledger was loaded: the source of its first posting,
+// and the header that has to be right above that posting to edit it.
+pub struct TxnSource {
+    pub file: PathBuf,
+    // counting from 1
+    pub line: usize,
+    pub account: String,
+    pub date: NaiveDate,
+    pub payee: String,
+    pub narration: String,
+}
+
+fn is_header(line: &str, txn: &TxnSource) -> bool {
+    let tokens = directives::tokens(line);
+    if tokens.first() != Some(&txn.date.format("%Y-%m-%d").to_string()) {
+        return false;
+    }
+    // `"Shop" "Lunch"`, or `"Lunch"` alone without a payee
+    let strings: Vec<&str> = tokens.iter().skip(2).map(String::as_str).collect();
+    strings.starts_with(&[txn.payee.as_str(), txn.narration.as_str()])
+        || (txn.payee.is_empty() && strings.starts_with(&[txn.narration.as_str()]))
+}
+
+// The index of the first posting of `txn` in `lines`, if it is still where
+// the ledger was loaded from: a posting of exactly `account`, below only
+// metadata and the header of the transaction.
+fn first_posting(lines: &[&str], txn: &TxnSource) -> Option<usize> {
+    let index = txn
+        .line
+        .checked_sub(1)
+        .filter(|index| *index < lines.len())?;
+    let indented = |line: &str| line.starts_with(char::is_whitespace);
+    if !indented(lines[index]) || directives::tokens(lines[index]).first() != Some(&txn.account) {
+        return None;
+    }
+    let header = (0..index).rev().find(|i| !indented(lines[*i]))?;
+    let only_meta = lines[header + 1..index]
+        .iter()
+        .all(|line| directives::meta_line(line).is_some());
+    if only_meta && is_header(lines[header], txn) {
+        Some(index)
+    } else {
+        None
+    }
+}
+
+// Adds `document: "path"` to a transaction, right above its first posting.
+// Fails with `InvalidData` if the transaction is no longer where it was.
+pub async fn add_txn_document(txn: &TxnSource, path: &str) -> io::Result<()> {
+    let content = tokio::fs::read_to_string(&txn.file).await?;
+    let mut lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let index = first_posting(&lines, txn)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "the transaction has moved"))?;
+    let posting = lines[index];
+    let indent = &posting[..posting.len() - posting.trim_start().len()];
+    let newline = if posting.ends_with("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let entry = format!("{}document: \"{}\"{}", indent, path, newline);
+    lines.insert(index, &entry);
+    // A temporary file first, so that a failed write cannot truncate the ledger.
+    let tmp = PathBuf::from(format!("{}.tmp", txn.file.display()));
+    tokio::fs::write(&tmp, lines.concat()).await?;
+    tokio::fs::rename(&tmp, &txn.file).await
+}
+
+fn meta_documents(meta: &Meta) -> Vec<String> {
+    meta.iter()
+        .filter(|(key, _)| key.to_string() == "document")
+        .map(|(_, (value, _))| value.to_string().trim_matches('"').to_string())
+        .collect()
+}
+
+fn directive_document(directive: &Directive) -> Option<Document> {
+    if directive.keyword != "document" {
+        return None;
+    }
+    match directive.args.as_slice() {
+        [account, path, ..] => Some(Document {
+            date: directive.date,
+            path: path.clone(),
+            accounts: vec![account.clone()],
+            description: None,
+        }),
+        _ => {
+            log::warn!(
+                "{}:{}: document needs an account and a path",
+                directive.file.display(),
+                directive.line
+            );
+            None
+        }
+    }
+}
+
+// Newest first, like the journal.
+pub fn list(ledger: &Ledger, directives: &[Directive], account: Option<&str>) -> Vec<Document> {
+    let mut documents: Vec<Document> = directives.iter().filter_map(directive_document).collect();
+    for txn in ledger.txns() {
+        let description = if txn.payee().is_empty() {
+            txn.narration().to_string()
+        } else if txn.narration().is_empty() {
+            txn.payee().to_string()
+        } else {
+            format!("{} {}", txn.payee(), txn.narration())
+        };
+        let accounts: Vec<String> = txn
+            .postings()
+            .iter()
+            .map(|posting| posting.account.to_string())
+            .collect();
+        for path in meta_documents(txn.meta()) {
+            documents.push(Document {
+                date: txn.date(),
+                path,
+                accounts: accounts.clone(),
+                description: Some(description.clone()),
+            });
+        }
+        for posting in txn.postings() {
+            for path in meta_documents(&posting.meta) {
+                documents.push(Document {
+                    date: txn.date(),
+                    path,
+                    accounts: vec![posting.account.to_string()],
+                    description: Some(description.clone()),
+                });
+            }
+        }
+    }
+    if let Some(account) = account {
+        documents.retain(|document| {
+            document
+                .accounts
+                .iter()
+                .any(|name| is_sub_account(name, account))
+        });
+    }
+    documents.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| a.path.cmp(&b.path)));
+    documents
+}
+
+// Streams the file in chunks instead of reading it into memory.
+pub async fn stream(path: &Path) -> io::Result<Response> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let len = file.metadata().await?.len();
+    let (mut sender, body) = Body::channel();
+    let name = path.display().to_string();
+    tokio::spawn(async move {
+        let mut buffer = vec![0; CHUNK_SIZE];
+        loop {
+            match file.read(&mut buffer).await {
+                Ok(0) => break,
+                Ok(n) => {
+                    if sender
+                        .send_data(Bytes::copy_from_slice(&buffer[..n]))
+                        .await
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    log::error!("Cannot read {}: {}", name, e);
+                    sender.abort();
+                    break;
+                }
+            }
+        }
+    });
+    let mime = mime_guess::from_path(path).first_or_octet_stream();
+    // Anything else, like HTML or SVG, could run scripts on our origin.
+    let inline = UPLOAD_TYPES
+        .iter()
+        .any(|(_, content_type, _)| *content_type == mime.as_ref());
+    let mut response = Response::new(body);
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, mime.as_ref().parse().unwrap());
+    headers.insert(CONTENT_LENGTH, len.into());
+    headers.insert(X_CONTENT_TYPE_OPTIONS, "nosniff".parse().unwrap());
+    let disposition = if inline { "inline" } else { "attachment" };
+    headers.insert(CONTENT_DISPOSITION, disposition.parse().unwrap());
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory per test, so that tests can run in parallel.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "lumi-server-documents-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn referenced(paths: &[&str]) -> Vec<Document> {
+        paths
+            .iter()
+            .map(|path| Document {
+                date: NaiveDate::from_ymd_opt(2021, 1, 2).unwrap(),
+                path: path.to_string(),
+                accounts: vec!["Assets:Cash".to_string()],
+                description: None,
+            })
+            .collect()
+    }
+
+    fn documents(dir: &Path) -> Documents {
+        let ledger = dir.join("main.beancount");
+        std::fs::write(&ledger, "").unwrap();
+        std::fs::write(dir.join("receipt.pdf"), b"%PDF-1.4").unwrap();
+        Documents::new(ledger.to_str().unwrap(), &[])
+    }
+
+    #[test]
+    fn resolves_referenced_documents() {
+        let dir = test_dir("referenced");
+        let documents = documents(&dir);
+        let file = documents
+            .resolve("receipt.pdf", &referenced(&["receipt.pdf"]))
+            .unwrap();
+        assert_eq!(file, dir.canonicalize().unwrap().join("receipt.pdf"));
+    }
+
+    #[test]
+    fn rejects_unreferenced_files() {
+        let dir = test_dir("unreferenced");
+        let documents = documents(&dir);
+        for path in ["main.beancount", "receipt.pdf"] {
+            let err = documents.resolve(path, &referenced(&[])).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::NotFound, "{}", path);
+        }
+    }
+
+    #[test]
+    fn rejects_parent_dirs() {
+        let dir = test_dir("parent");
+        let documents = documents(&dir);
+        let path = "../receipt.pdf";
+        let err = documents.resolve(path, &referenced(&[path])).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn rejects_absolute_paths_outside_roots() {
+        let dir = test_dir("absolute");
+        let outside = test_dir("absolute-outside");
+        std::fs::write(outside.join("secret.pdf"), b"%PDF-1.4").unwrap();
+        let documents = documents(&dir);
+        let path = outside.join("secret.pdf");
+        let path = path.to_str().unwrap();
+        let err = documents.resolve(path, &referenced(&[path])).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_symlinks_leaving_roots() {
+        let dir = test_dir("symlink");
+        let outside = test_dir("symlink-outside");
+        std::fs::write(outside.join("secret.pdf"), b"%PDF-1.4").unwrap();
+        std::os::unix::fs::symlink(outside.join("secret.pdf"), dir.join("link.pdf")).unwrap();
+        let documents = documents(&dir);
+        let err = documents
+            .resolve("link.pdf", &referenced(&["link.pdf"]))
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    fn upload(filename: &str) -> UploadOptions {
+        UploadOptions {
+            account: "Expenses:Food".to_string(),
+            date: NaiveDate::from_ymd_opt(2021, 1, 2).unwrap(),
+            filename: filename.to_string(),
+            src_file: None,
+            src_line: None,
+        }
+    }
+
+    #[test]
+    fn names_uploads_after_date_and_account() {
+        assert_eq!(
+            upload_name(&upload("receipt.pdf")).unwrap(),
+            "2021-01-02.Expenses.Food.receipt.pdf"
+        );
+    }
+
+    #[test]
+    fn rejects_upload_names_that_are_not_plain() {
+        for name in [
+            "../receipt.pdf",
+            "dir/receipt.pdf",
+            "/etc/passwd",
+            "..\\receipt.pdf",
+            "..",
+            ".",
+            "",
+            ".hidden.pdf",
+            "re\"ceipt.pdf",
+            "receipt\n.pdf",
+        ] {
+            assert!(upload_name(&upload(name)).is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn checks_uploaded_files() {
+        assert!(check_upload("receipt.pdf", Some("application/pdf"), b"%PDF-1.4").is_ok());
+        assert!(check_upload("receipt.PDF", None, b"%PDF-1.4").is_ok());
+        assert!(check_upload(
+            "scan.png",
+            Some("application/octet-stream"),
+            b"\x89PNG\r\n\x1a\n"
+        )
+        .is_ok());
+        // empty files
+        assert!(check_upload("receipt.pdf", None, b"").is_err());
+        // contents that do not match the extension
+        assert!(check_upload("receipt.pdf", None, b"<html>").is_err());
+        assert!(check_upload("receipt.png", None, b"%PDF-1.4").is_err());
+        // content types that do not match the extension
+        assert!(check_upload("receipt.pdf", Some("text/html"), b"%PDF-1.4").is_err());
+        // anything but PDFs and images
+        assert!(check_upload("page.html", Some("text/html"), b"<html>").is_err());
+        assert!(check_upload("receipt", None, b"%PDF-1.4").is_err());
+    }
+
+    #[test]
+    fn writes_short_entry_paths_next_to_the_ledger() {
+        let dir = test_dir("entry-path").canonicalize().unwrap();
+        let ledger = dir.join("main.beancount");
+        std::fs::write(&ledger, "").unwrap();
+        let ledger = ledger.to_str().unwrap();
+        assert_eq!(entry_path(ledger, &dir.join("receipt.pdf")), "receipt.pdf");
+        let other = dir.join("scans").join("receipt.pdf");
+        assert_eq!(entry_path(ledger, &other), other.display().to_string());
+    }
+
+    fn txn_source(file: &Path, line: usize, account: &str) -> TxnSource {
+        TxnSource {
+            file: file.to_path_buf(),
+            line,
+            account: account.to_string(),
+            date: NaiveDate::from_ymd_opt(2021, 1, 2).unwrap(),
+            payee: String::new(),
+            narration: "Shop".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn adds_documents_to_transactions() {
+        let dir = test_dir("txn-document");
+        let ledger = dir.join("main.beancount");
+        let content = "2021-01-02 * \"Shop\"\n  Assets:Cash -10 USD\n  Expenses:Food\n";
+        std::fs::write(&ledger, content).unwrap();
+        let txn = txn_source(&ledger, 2, "Assets:Cash");
+        add_txn_document(&txn, "receipt.pdf").await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&ledger).unwrap(),
+            "2021-01-02 * \"Shop\"\n  document: \"receipt.pdf\"\n  Assets:Cash -10 USD\n  Expenses:Food\n"
+        );
+        // The first posting is no longer at line 2.
+        let err = add_txn_document(&txn, "receipt.pdf").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        // Below the new metadata, it is still the first posting.
+        add_txn_document(&txn_source(&ledger, 3, "Assets:Cash"), "invoice.pdf")
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn finds_only_the_first_posting_of_the_same_transaction() {
+        let file = Path::new("main.beancount");
+        let lines: Vec<&str> = concat!(
+            "2021-01-02 * \"Shop\"\r\n",
+            "  note: \"weekly\"\r\n",
+            "  Assets:Cash:Sub -10 USD\r\n",
+            "  Expenses:Food\r\n",
+            "\r\n",
+            "2021-01-02 * \"Market\" \"Shop\"\r\n",
+            "  Assets:Cash -10 USD\r\n",
+            "  Expenses:Food\r\n",
+        )
+        .split_inclusive('\n')
+        .collect();
+        assert_eq!(
+            first_posting(&lines, &txn_source(file, 3, "Assets:Cash:Sub")),
+            Some(2)
+        );
+        // a prefix of the account
+        assert_eq!(
+            first_posting(&lines, &txn_source(file, 3, "Assets:Cash")),
+            None
+        );
+        // the second posting
+        assert_eq!(
+            first_posting(&lines, &txn_source(file, 4, "Expenses:Food")),
+            None
+        );
+        // a header line and a blank line
+        assert_eq!(
+            first_posting(&lines, &txn_source(file, 1, "Assets:Cash")),
+            None
+        );
+        assert_eq!(
+            first_posting(&lines, &txn_source(file, 5, "Assets:Cash")),
+            None
+        );
+        // the posting of another transaction on the same day
+        assert_eq!(
+            first_posting(&lines, &txn_source(file, 7, "Assets:Cash")),
+            None
+        );
+        let mut market = txn_source(file, 7, "Assets:Cash");
+        market.payee = "Market".to_string();
+        assert_eq!(first_posting(&lines, &market), Some(6));
+        assert_eq!(
+            first_posting(&lines, &txn_source(file, 99, "Assets:Cash")),
+            None
+        );
+    }
+}
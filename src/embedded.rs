@@ -0,0 +1,125 @@
+use crate::assets::PAGES;
+use crate::compression::{self, Encoding};
+use headers::{ContentType, HeaderMapExt};
+use include_dir::{include_dir, Dir};
+use std::collections::HashSet;
+use warp::http::header::{HeaderValue, CACHE_CONTROL, CONTENT_ENCODING, ETAG, VARY};
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Filter, Rejection};
+
+include!(concat!(env!("OUT_DIR"), "/etags.rs"));
+
+pub static WEB_DIR: Dir = include_dir!("lumi-web/dist");
+
+fn get_file(path: &str) -> Option<&'static [u8]> {
+    WEB_DIR.get_file(path).map(|f| f.contents)
+}
+
+fn get_etag(path: &str) -> Option<&'static str> {
+    ETAGS
+        .binary_search_by_key(&path, |&(p, _)| p)
+        .ok()
+        .map(|i| ETAGS[i].1)
+}
+
+// trunk names its outputs like `index-0123456789abcdef.js` and
+// `lumi-web-0123456789abcdef_bg.wasm`
+fn is_fingerprinted(path: &str) -> bool {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    let stem = stem.strip_suffix("_bg").unwrap_or(stem);
+    match stem.rsplit_once('-') {
+        Some((_, hash)) => hash.len() == 16 && hash.chars().all(|c| c.is_ascii_hexdigit()),
+        None => false,
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AssetRequest {
+    encoding: Encoding,
+    if_none_match: Option<String>,
+}
+
+fn asset_request() -> impl Filter<Extract = (AssetRequest,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("accept-encoding")
+        .and(warp::header::optional::<String>("if-none-match"))
+        .map(
+            |accept: Option<String>, if_none_match: Option<String>| AssetRequest {
+                encoding: compression::preferred(accept.as_deref()),
+                if_none_match,
+            },
+        )
+}
+
+fn file_response(path: &str, request: &AssetRequest) -> Option<Response> {
+    let contents = get_file(path)?;
+    let compressed = request
+        .encoding
+        .extension()
+        .and_then(|ext| get_file(&format!("{}{}", path, ext)));
+    let encoding = if compressed.is_some() {
+        request.encoding
+    } else {
+        Encoding::Identity
+    };
+    let etag = get_etag(path).map(|hash| match encoding {
+        Encoding::Identity => format!("\"{}\"", hash),
+        _ => format!("\"{}-{}\"", hash, encoding.name()),
+    });
+    let not_modified = match (&etag, &request.if_none_match) {
+        (Some(etag), Some(if_none_match)) => if_none_match
+            .split(',')
+            .any(|tag| tag.trim().trim_start_matches("W/") == etag || tag.trim() == "*"),
+        _ => false,
+    };
+    let mut resp = if not_modified {
+        let mut resp = Response::default();
+        *resp.status_mut() = StatusCode::NOT_MODIFIED;
+        resp
+    } else {
+        let mut resp = Response::new(compressed.unwrap_or(contents).into());
+        let mime = mime_guess::from_path(path).first_or_octet_stream();
+        resp.headers_mut().typed_insert(ContentType::from(mime));
+        if encoding != Encoding::Identity {
+            resp.headers_mut()
+                .insert(CONTENT_ENCODING, HeaderValue::from_static(encoding.name()));
+        }
+        resp
+    };
+    let headers = resp.headers_mut();
+    if let Some(etag) = etag {
+        headers.insert(ETAG, HeaderValue::from_str(&etag).unwrap());
+    }
+    let cache_control = if is_fingerprinted(path) {
+        "public, max-age=31536000, immutable"
+    } else {
+        "no-cache"
+    };
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static(cache_control));
+    headers.insert(VARY, HeaderValue::from_static("accept-encoding"));
+    Some(resp)
+}
+
+pub fn routes() -> impl Filter<Extract = (Response,), Error = Rejection> + Clone {
+    let root_index = warp::path::end()
+        .and(asset_request())
+        .map(|request: AssetRequest| file_response("index.html", &request).unwrap());
+
+    let pages: HashSet<&str> = PAGES.into_iter().collect();
+    let file =
+        warp::path::param()
+            .and(asset_request())
+            .map(move |path: String, request: AssetRequest| {
+                if let Some(resp) = file_response(&path, &request) {
+                    resp
+                } else if pages.contains(path.as_str()) {
+                    file_response("index.html", &request).unwrap()
+                } else {
+                    let mut resp = Response::default();
+                    *resp.status_mut() = StatusCode::NOT_FOUND;
+                    resp
+                }
+            });
+    warp::get().and(root_index.or(file).unify())
+}
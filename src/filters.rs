@@ -1,105 +1,374 @@
+use super::documents::{self, Documents};
 use super::handlers;
-use lumi::{Error, Ledger};
-use lumi_server_defs::{FilterOptions, TrieOptions};
+use super::metrics::Metrics;
+use super::rejection;
+use super::snapshot::Store;
+use super::views::Views;
+use lumi_server_defs::{
+    BudgetOptions, ChangesOptions, CompleteOptions, DocumentFileOptions, DocumentOptions,
+    FilterOptions, ServerOptions, TrieOptions, UploadOptions, ViewOptions,
+};
+use percent_encoding::percent_decode_str;
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use warp::Filter;
+use warp::filters::BoxedFilter;
+use warp::reply::Response;
+use warp::{Filter, Reply};
 
-fn with_ledger(
-    ledger: Arc<RwLock<Ledger>>,
-) -> impl Filter<Extract = (Arc<RwLock<Ledger>>,), Error = std::convert::Infallible> + Clone {
-    warp::any().map(move || ledger.clone())
+fn with_store(
+    store: Arc<Store>,
+) -> impl Filter<Extract = (Arc<Store>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || store.clone())
 }
 
-fn with_errors(
-    errors: Arc<RwLock<Vec<Error>>>,
-) -> impl Filter<Extract = (Arc<RwLock<Vec<Error>>>,), Error = std::convert::Infallible> + Clone {
-    warp::any().map(move || errors.clone())
+fn with_metrics(
+    metrics: Arc<Metrics>,
+) -> impl Filter<Extract = (Arc<Metrics>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || metrics.clone())
 }
 
-fn with_path(
-    path: String,
-) -> impl Filter<Extract = (String,), Error = std::convert::Infallible> + Clone {
-    warp::any().map(move || path.clone())
+fn with_views(
+    views: Arc<Views>,
+) -> impl Filter<Extract = (Arc<Views>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || views.clone())
+}
+
+fn with_documents(
+    documents: Arc<Documents>,
+) -> impl Filter<Extract = (Arc<Documents>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || documents.clone())
+}
+
+// warp hands over path parameters as they are in the URL, where clients
+// escape characters like spaces in account and commodity names.
+fn name_param() -> impl Filter<Extract = (String,), Error = warp::Rejection> + Clone {
+    warp::path::param()
+        .map(|param: String| percent_decode_str(&param).decode_utf8_lossy().into_owned())
+}
+
+// Gives differently composed filters one type.
+fn boxed<F, R>(filter: F) -> BoxedFilter<(Response,)>
+where
+    F: Filter<Extract = (R,), Error = warp::Rejection> + Clone + Send + Sync + 'static,
+    R: Reply,
+{
+    filter.map(|reply: R| reply.into_response()).boxed()
 }
 
 pub fn refresh(
-    ledger: Arc<RwLock<Ledger>>,
-    errors: Arc<RwLock<Vec<Error>>>,
-    path: String,
+    store: Arc<Store>,
+    metrics: Arc<Metrics>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path("refresh")
-        .and(warp::get())
-        .and(with_ledger(ledger))
-        .and(with_errors(errors))
-        .and(with_path(path))
+        .and(warp::post())
+        .and(with_store(store))
+        .and(with_metrics(metrics))
         .and_then(handlers::refresh)
 }
 
+// Every route of `ledger_api`, with `:` before path parameters. Request
+// metrics are labelled with these, and a test checks them against the OpenAPI
+// document, so a route added below belongs here as well.
+pub const API_ROUTES: [(&str, &str); 21] = [
+    ("post", "/api/refresh"),
+    ("get", "/api/balances"),
+    ("get", "/api/journal"),
+    ("get", "/api/account/:name"),
+    ("get", "/api/account_info/:name"),
+    ("get", "/api/accounts"),
+    ("get", "/api/budget"),
+    ("get", "/api/trie/:root"),
+    ("get", "/api/changes"),
+    ("get", "/api/complete"),
+    ("get", "/api/errors"),
+    ("get", "/api/commodities"),
+    ("get", "/api/commodity/:name"),
+    ("get", "/api/documents"),
+    ("get", "/api/documents/file"),
+    ("post", "/api/documents"),
+    ("get", "/api/views"),
+    ("post", "/api/views"),
+    ("delete", "/api/views"),
+    ("get", "/api/options"),
+    ("get", "/api/openapi.json"),
+];
+
+// On a server started with --read-only, routes that change the ledger or
+// the saved views are not mounted at all.
 pub fn ledger_api(
-    ledger: Arc<RwLock<Ledger>>,
-    errors: Arc<RwLock<Vec<Error>>>,
-    path: &str,
+    store: Arc<Store>,
+    views: Arc<Views>,
+    documents: Arc<Documents>,
+    metrics: Arc<Metrics>,
+    options: ServerOptions,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
-    warp::path("api").and(
-        refresh(ledger.clone(), errors.clone(), path.to_owned())
-            .or(get_balances(ledger.clone()))
-            .or(get_journal_all(ledger.clone()))
-            .or(get_journal(ledger.clone()))
-            .or(get_trie(ledger))
-            .or(get_errors(errors)),
-    )
+    let read_only = options.read_only;
+    let reads = refresh(store.clone(), metrics)
+        .or(get_balances(store.clone()))
+        .or(get_journal_all(store.clone()))
+        .or(get_journal(store.clone()))
+        .or(get_account_info(store.clone()))
+        .or(get_accounts(store.clone()))
+        .or(get_budget(store.clone()))
+        .or(get_trie(store.clone()))
+        .or(get_changes(store.clone()))
+        .or(get_complete(store.clone()))
+        .or(get_errors(store.clone()))
+        .or(get_commodities(store.clone()))
+        .or(get_commodity(store.clone()))
+        .or(get_documents(store.clone()))
+        .or(get_document_file(store.clone(), documents.clone()))
+        .or(get_views(views.clone()))
+        .or(get_options(options))
+        .or(get_openapi());
+    let reads = boxed(reads);
+    let api = if read_only {
+        reads
+    } else {
+        boxed(
+            reads
+                .or(post_document(store, documents))
+                .or(post_view(views.clone()))
+                .or(delete_view(views)),
+        )
+    };
+    warp::path("api").and(api.recover(rejection::recover))
 }
 
 pub fn get_balances(
-    ledger: Arc<RwLock<Ledger>>,
+    store: Arc<Store>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path("balances")
         .and(warp::get())
-        .and(with_ledger(ledger))
+        .and(with_store(store))
         .and_then(handlers::balances)
 }
 
+pub fn get_changes(
+    store: Arc<Store>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path("changes")
+        .and(warp::get())
+        .and(warp::query::<ChangesOptions>())
+        .and(with_store(store))
+        .and_then(handlers::changes)
+}
+
+pub fn get_complete(
+    store: Arc<Store>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path("complete")
+        .and(warp::get())
+        .and(warp::query::<CompleteOptions>())
+        .and(with_store(store))
+        .and_then(handlers::complete)
+}
+
+pub fn get_views(
+    views: Arc<Views>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path("views")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_views(views))
+        .and_then(handlers::views)
+}
+
+pub fn post_view(
+    views: Arc<Views>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path("views")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::body::content_length_limit(16 * 1024))
+        .and(warp::body::json())
+        .and(with_views(views))
+        .and_then(handlers::save_view)
+}
+
+pub fn delete_view(
+    views: Arc<Views>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path("views")
+        .and(warp::path::end())
+        .and(warp::delete())
+        .and(warp::query::<ViewOptions>())
+        .and(with_views(views))
+        .and_then(handlers::delete_view)
+}
+
+pub fn get_documents(
+    store: Arc<Store>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path("documents")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query::<DocumentOptions>())
+        .and(with_store(store))
+        .and_then(handlers::documents)
+}
+
+pub fn get_document_file(
+    store: Arc<Store>,
+    documents: Arc<Documents>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path("documents")
+        .and(warp::path("file"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(warp::query::<DocumentFileOptions>())
+        .and(with_store(store))
+        .and(with_documents(documents))
+        .and_then(handlers::document_file)
+}
+
+pub fn post_document(
+    store: Arc<Store>,
+    documents: Arc<Documents>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path("documents")
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(warp::query::<UploadOptions>())
+        .and(warp::header::optional::<String>("content-type"))
+        .and(warp::body::content_length_limit(documents::MAX_UPLOAD_SIZE))
+        .and(warp::body::bytes())
+        .and(with_store(store))
+        .and(with_documents(documents))
+        .and_then(handlers::upload_document)
+}
+
 pub fn get_errors(
-    errors: Arc<RwLock<Vec<Error>>>,
+    store: Arc<Store>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::path("errors")
         .and(warp::get())
-        .and(with_errors(errors))
+        .and(with_store(store))
         .and_then(handlers::errors)
 }
 
 pub fn get_trie(
-    ledger: Arc<RwLock<Ledger>>,
+    store: Arc<Store>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::get()
         .and(warp::path("trie"))
-        .and(warp::path::param())
+        .and(name_param())
         .and(warp::query::<TrieOptions>())
-        .and(with_ledger(ledger))
+        .and(with_store(store))
         .and_then(handlers::trie)
 }
 
 pub fn get_journal(
-    ledger: Arc<RwLock<Ledger>>,
+    store: Arc<Store>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::get()
         .and(warp::path("account"))
-        .and(warp::path::param())
+        .and(name_param())
         .and(warp::query::<FilterOptions>())
-        .and(with_ledger(ledger))
-        .and_then(|account, options, ledger| {
-            handlers::account_journal(Some(account), options, ledger)
+        .and(with_store(store))
+        .and_then(|account, options, store| {
+            handlers::account_journal(Some(account), options, store)
         })
 }
 
 pub fn get_journal_all(
-    ledger: Arc<RwLock<Ledger>>,
+    store: Arc<Store>,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
     warp::get()
         .and(warp::path("journal"))
         .and(warp::path::end())
         .and(warp::query::<FilterOptions>())
-        .and(with_ledger(ledger))
-        .and_then(|options, ledger| handlers::account_journal(None, options, ledger))
+        .and(with_store(store))
+        .and_then(|options, store| handlers::account_journal(None, options, store))
+}
+
+pub fn get_account_info(
+    store: Arc<Store>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("account_info"))
+        .and(name_param())
+        .and(with_store(store))
+        .and_then(handlers::account_info)
+}
+
+pub fn get_accounts(
+    store: Arc<Store>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path("accounts")
+        .and(warp::get())
+        .and(with_store(store))
+        .and_then(handlers::accounts)
+}
+
+pub fn get_commodities(
+    store: Arc<Store>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path("commodities")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_store(store))
+        .and_then(handlers::commodities)
+}
+
+pub fn get_commodity(
+    store: Arc<Store>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::get()
+        .and(warp::path("commodity"))
+        .and(name_param())
+        .and(warp::path::end())
+        .and(with_store(store))
+        .and_then(handlers::commodity)
+}
+
+pub fn get_budget(
+    store: Arc<Store>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path("budget")
+        .and(warp::get())
+        .and(warp::query::<BudgetOptions>())
+        .and(with_store(store))
+        .and_then(handlers::budget)
+}
+
+pub fn monitoring(
+    metrics: Arc<Metrics>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    get_metrics(metrics.clone()).or(get_healthz(metrics))
+}
+
+pub fn get_metrics(
+    metrics: Arc<Metrics>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path("metrics")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_metrics(metrics))
+        .and_then(handlers::metrics)
+}
+
+pub fn get_healthz(
+    metrics: Arc<Metrics>,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path("healthz")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_metrics(metrics))
+        .and_then(handlers::healthz)
+}
+
+pub fn get_options(
+    options: ServerOptions,
+) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+    warp::path("options")
+        .and(warp::get())
+        .and(warp::any().map(move || options.clone()))
+        .and_then(handlers::options)
+}
+
+pub fn get_openapi() -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone
+{
+    warp::path("openapi.json")
+        .and(warp::get())
+        .and_then(handlers::openapi)
 }
@@ -1,34 +1,71 @@
+use crate::budget::{self, ReportPeriod};
+use crate::changes;
+use crate::complete;
+use crate::documents::{self, Documents};
+use crate::metrics::Metrics;
+use crate::openapi;
+use crate::rejection;
+use crate::snapshot::Store;
+use crate::views::Views;
 use chrono::Datelike;
-use lumi::{BalanceSheet, Error, Ledger, Transaction, TxnFlag};
+use lumi::{BalanceSheet, Ledger, Meta, Transaction, TxnFlag, UnitCost};
+pub(crate) use lumi_server_defs::is_sub_account;
 use lumi_server_defs::{
-    FilterOptions, JournalItem, Position, RefreshTime, TrieNode, TrieOptions, TrieTable,
-    TrieTableRow,
+    AccountBalance, AccountOverview, AccountSummary, BalanceAssertion, BudgetOptions, ChangeLog,
+    ChangesOptions, CompleteOptions, DocumentFileOptions, DocumentOptions, FilterOptions,
+    JournalItem, Position, RefreshTime, ServerOptions, TrieNode, TrieOptions, TrieTable,
+    TrieTableRow, UploadOptions, UploadedDocument, View, ViewOptions,
 };
 use rust_decimal::Decimal;
 use std::sync::Arc;
 use std::{
     collections::{HashMap, HashSet},
     convert::Infallible,
+    io,
 };
-use tokio::sync::RwLock;
+use warp::hyper::body::Bytes;
+use warp::Rejection;
 
 pub async fn refresh(
-    ledger: Arc<RwLock<Ledger>>,
-    errors: Arc<RwLock<Vec<Error>>>,
-    path: String,
-) -> Result<impl warp::Reply, Infallible> {
-    let (new_ledger, new_errors) = Ledger::from_file(&path);
-    let (mut ledger, mut errors) = (ledger.write().await, errors.write().await);
-    // (ledger, errors) = (new_ledger, new_errors);
-    *ledger = new_ledger;
-    *errors = new_errors;
-    let timestamp = chrono::Utc::now().timestamp();
-    let reply = RefreshTime { timestamp };
-    log::info!("Ledger refreshed: {}", timestamp);
+    store: Arc<Store>,
+    metrics: Arc<Metrics>,
+) -> Result<impl warp::Reply, Rejection> {
+    metrics.record_refresh();
+    let snapshot = store.reload().await.map_err(|e| {
+        log::error!("Cannot reload ledger: {}", e);
+        rejection::internal(format!("Cannot reload ledger: {}", e))
+    })?;
+    let reply = RefreshTime {
+        timestamp: snapshot.loaded_at,
+        generation: snapshot.generation,
+    };
+    log::info!(
+        "Ledger refreshed: generation {} at {}",
+        snapshot.generation,
+        snapshot.loaded_at
+    );
     Ok(warp::reply::json(&reply))
 }
 
-fn balance_sheet_to_list(sheet: &BalanceSheet) -> HashMap<String, Vec<Position>> {
+pub async fn metrics(metrics: Arc<Metrics>) -> Result<impl warp::Reply, Infallible> {
+    Ok(warp::reply::with_header(
+        metrics.render(),
+        "content-type",
+        prometheus::TEXT_FORMAT,
+    ))
+}
+
+pub async fn healthz(metrics: Arc<Metrics>) -> Result<impl warp::Reply, Infallible> {
+    let health = metrics.health();
+    let status = if health.ok {
+        warp::http::StatusCode::OK
+    } else {
+        warp::http::StatusCode::SERVICE_UNAVAILABLE
+    };
+    Ok(warp::reply::with_status(warp::reply::json(&health), status))
+}
+
+pub fn balance_sheet_to_list(sheet: &BalanceSheet) -> HashMap<String, Vec<Position>> {
     let mut result = HashMap::new();
     for (account, account_map) in sheet {
         let list = result.entry(account.to_string()).or_insert_with(Vec::new);
@@ -76,7 +113,7 @@ fn build_trie_table_helper<'s, 'r: 's>(
     }
 }
 
-fn build_trie_table<'s, 'r: 's>(
+pub fn build_trie_table<'s, 'r: 's>(
     ledger: &'s Ledger,
     root_account: &'r str,
     options: TrieOptions,
@@ -140,63 +177,566 @@ pub fn build_trie<'s>(
 pub async fn trie(
     root_account: String,
     options: TrieOptions,
-    ledger: Arc<RwLock<Ledger>>,
+    store: Arc<Store>,
+) -> Result<impl warp::Reply, Rejection> {
+    let snapshot = store.snapshot();
+    let ledger = &snapshot.ledger;
+    match build_trie_table(ledger, &root_account, options) {
+        Some(trie_table) => Ok(warp::reply::json(&trie_table)),
+        None => Err(rejection::not_found(format!(
+            "Unknown root account {}",
+            root_account
+        ))),
+    }
+}
+
+pub async fn options(options: ServerOptions) -> Result<impl warp::Reply, Infallible> {
+    Ok(warp::reply::json(&options))
+}
+
+pub async fn views(views: Arc<Views>) -> Result<impl warp::Reply, Infallible> {
+    Ok(warp::reply::json(&views.list().await))
+}
+
+// Every key of `query` must be an option of `T`. Options that are set are
+// serialized again, so a key that is lost on the way is unknown.
+fn check_query<T>(query: &str) -> Result<(), String>
+where
+    T: serde::de::DeserializeOwned + serde::Serialize,
+{
+    let options: T = serde_urlencoded::from_str(query).map_err(|e| e.to_string())?;
+    let known = serde_urlencoded::to_string(&options).map_err(|e| e.to_string())?;
+    let known: Vec<(String, String)> =
+        serde_urlencoded::from_str(&known).map_err(|e| e.to_string())?;
+    let keys: Vec<(String, String)> =
+        serde_urlencoded::from_str(query).map_err(|e| e.to_string())?;
+    match keys
+        .iter()
+        .find(|(key, _)| !known.iter().any(|(k, _)| k == key))
+    {
+        Some((key, _)) => Err(format!("Unknown option {}", key)),
+        None => Ok(()),
+    }
+}
+
+// A view must point to a page of the web UI, with the options of that page.
+fn check_view(view: &View) -> Result<(), String> {
+    let segments: Vec<&str> = view
+        .path
+        .strip_prefix('/')
+        .ok_or_else(|| format!("Invalid view path {}", view.path))?
+        .split('/')
+        .collect();
+    let query = view.query.as_str();
+    let checked = match segments.as_slice() {
+        ["journal"] => check_query::<FilterOptions>(query),
+        ["account", name] if !name.is_empty() => check_query::<FilterOptions>(query),
+        [""] | ["balance_sheet"] | ["income"] => check_query::<TrieOptions>(query),
+        ["budget"] => check_query::<BudgetOptions>(query),
+        ["holdings"] | ["errors"] | ["commodity", _] if query.is_empty() => Ok(()),
+        ["holdings"] | ["errors"] | ["commodity", _] => Err("the page has no options".to_string()),
+        _ => return Err(format!("Invalid view path {}", view.path)),
+    };
+    checked.map_err(|e| format!("Invalid view query {}: {}", view.query, e))
+}
+
+pub async fn save_view(view: View, views: Arc<Views>) -> Result<impl warp::Reply, Rejection> {
+    if view.name.trim().is_empty() {
+        return Err(rejection::bad_request("View name is empty".to_string()));
+    }
+    check_view(&view).map_err(rejection::bad_request)?;
+    match views.save(view).await {
+        Ok(list) => Ok(warp::reply::json(&list)),
+        Err(e) => Err(rejection::internal(format!("Cannot save views: {}", e))),
+    }
+}
+
+pub async fn delete_view(
+    options: ViewOptions,
+    views: Arc<Views>,
+) -> Result<impl warp::Reply, Rejection> {
+    match views.delete(&options.name).await {
+        Ok(Some(list)) => Ok(warp::reply::json(&list)),
+        Ok(None) => Err(rejection::not_found(format!(
+            "Unknown view {}",
+            options.name
+        ))),
+        Err(e) => Err(rejection::internal(format!("Cannot save views: {}", e))),
+    }
+}
+
+pub async fn changes(
+    options: ChangesOptions,
+    store: Arc<Store>,
 ) -> Result<impl warp::Reply, Infallible> {
-    let ledger = ledger.read().await;
-    let trie_table = build_trie_table(&ledger, &root_account, options);
-    let result = trie_table.unwrap_or_default();
-    Ok(warp::reply::json(&result))
+    let snapshot = store.snapshot();
+    let since = options.since.unwrap_or(snapshot.generation);
+    let truncated = snapshot
+        .history
+        .first()
+        .map_or(false, |oldest| since < oldest.generation - 1);
+    let log = ChangeLog {
+        generation: snapshot.generation,
+        truncated,
+        changes: snapshot
+            .history
+            .iter()
+            .filter(|changes| changes.generation > since)
+            .map(|changes| changes.as_ref())
+            .collect(),
+    };
+    Ok(warp::reply::json(&log))
+}
+
+pub async fn complete(
+    options: CompleteOptions,
+    store: Arc<Store>,
+) -> Result<impl warp::Reply, Infallible> {
+    let snapshot = store.snapshot();
+    Ok(warp::reply::json(&complete::complete(
+        &snapshot.ledger,
+        &options,
+    )))
+}
+
+pub async fn openapi() -> Result<impl warp::Reply, Infallible> {
+    Ok(warp::reply::json(&openapi::document()))
+}
+
+pub async fn commodities(store: Arc<Store>) -> Result<impl warp::Reply, Infallible> {
+    let snapshot = store.snapshot();
+    Ok(warp::reply::json(&snapshot.commodities.list()))
+}
+
+pub async fn commodity(name: String, store: Arc<Store>) -> Result<impl warp::Reply, Rejection> {
+    let snapshot = store.snapshot();
+    match snapshot.commodities.details(&snapshot.ledger, &name) {
+        Some(details) => Ok(warp::reply::json(&details)),
+        None => Err(rejection::not_found(format!("Unknown commodity {}", name))),
+    }
+}
+
+pub async fn documents(
+    options: DocumentOptions,
+    store: Arc<Store>,
+) -> Result<impl warp::Reply, Infallible> {
+    let snapshot = store.snapshot();
+    Ok(warp::reply::json(&documents::list(
+        &snapshot.ledger,
+        &snapshot.directives,
+        options.account.as_deref(),
+    )))
+}
+
+// Serves only files the current ledger refers to, so that the ledger itself
+// and anything else next to it stays private.
+pub async fn document_file(
+    options: DocumentFileOptions,
+    store: Arc<Store>,
+    documents: Arc<Documents>,
+) -> Result<impl warp::Reply, Rejection> {
+    let snapshot = store.snapshot();
+    let referenced = documents::list(&snapshot.ledger, &snapshot.directives, None);
+    let path = documents
+        .resolve(&options.path, &referenced)
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::PermissionDenied => rejection::forbidden(format!(
+                "Document {} is outside of the document roots",
+                options.path
+            )),
+            _ => rejection::not_found(format!("Unknown document {}", options.path)),
+        })?;
+    documents::stream(&path)
+        .await
+        .map_err(|e| rejection::internal(format!("Cannot read {}: {}", options.path, e)))
+}
+
+// Stores the file in the first document root, adds it to the ledger, either
+// to a transaction or as a `document` entry, and reloads the ledger.
+pub async fn upload_document(
+    options: UploadOptions,
+    content_type: Option<String>,
+    body: Bytes,
+    store: Arc<Store>,
+    documents: Arc<Documents>,
+) -> Result<impl warp::Reply, Rejection> {
+    // Before the snapshot, so that it has the edits of earlier uploads.
+    let _writing = store.lock_writes().await;
+    let snapshot = store.snapshot();
+    let known_account = snapshot
+        .ledger
+        .accounts()
+        .keys()
+        .any(|account| **account == *options.account);
+    if !known_account {
+        return Err(rejection::bad_request(format!(
+            "Unknown account {}",
+            options.account
+        )));
+    }
+    let txn = match (&options.src_file, options.src_line) {
+        (Some(file), Some(line)) => {
+            let (txn, first_posting) = snapshot
+                .ledger
+                .txns()
+                .iter()
+                .filter(|txn| txn.date() == options.date)
+                .filter_map(|txn| txn.postings().first().map(|posting| (txn, posting)))
+                .find(|(_, posting)| {
+                    posting.src.file.to_string() == *file && posting.src.start.line == line
+                })
+                .ok_or_else(|| {
+                    rejection::bad_request(format!("No transaction at {}:{}", file, line))
+                })?;
+            Some(documents::TxnSource {
+                file: file.into(),
+                line,
+                account: first_posting.account.to_string(),
+                date: txn.date(),
+                payee: txn.payee().to_string(),
+                narration: txn.narration().to_string(),
+            })
+        }
+        (None, None) => None,
+        _ => {
+            return Err(rejection::bad_request(
+                "src_file and src_line have to be given together".to_string(),
+            ))
+        }
+    };
+    let name = documents::upload_name(&options).map_err(rejection::bad_request)?;
+    documents::check_upload(&options.filename, content_type.as_deref(), &body)
+        .map_err(rejection::bad_request)?;
+    let file = documents
+        .save_upload(&name, &body)
+        .await
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => {
+                rejection::bad_request(format!("Document {} already exists", name))
+            }
+            _ => rejection::internal(format!("Cannot save {}: {}", name, e)),
+        })?;
+    let path = documents::entry_path(store.path(), &file);
+    let added = match &txn {
+        Some(txn) => documents::add_txn_document(txn, &path).await,
+        None => documents::append_entry(store.path(), options.date, &options.account, &path).await,
+    };
+    if let Err(e) = added {
+        let _ = tokio::fs::remove_file(&file).await;
+        return Err(rejection::internal(format!(
+            "Cannot add {} to the ledger: {}",
+            name, e
+        )));
+    }
+    let snapshot = store.reload_after_write().await.map_err(|e| {
+        log::error!("Cannot reload ledger: {}", e);
+        rejection::internal(format!("Cannot reload ledger: {}", e))
+    })?;
+    // As the ledger lists it now: with the accounts of the transaction it
+    // was attached to, or the account of the new `document` entry.
+    let document = documents::list(&snapshot.ledger, &snapshot.directives, None)
+        .into_iter()
+        .find(|document| document.path == path && document.date == options.date)
+        .ok_or_else(|| rejection::internal(format!("Document {} was not loaded", path)))?;
+    match &txn {
+        Some(txn) => log::info!(
+            "Document {} added to {}:{}",
+            path,
+            txn.file.display(),
+            txn.line
+        ),
+        None => log::info!("Document {} added to {}", path, options.account),
+    }
+    Ok(warp::reply::json(&UploadedDocument {
+        document,
+        refresh: RefreshTime {
+            timestamp: snapshot.loaded_at,
+            generation: snapshot.generation,
+        },
+    }))
 }
 
-pub async fn errors(errors: Arc<RwLock<Vec<Error>>>) -> Result<impl warp::Reply, Infallible> {
-    let errors = errors.read().await;
-    Ok(warp::reply::json(&*errors))
+pub async fn errors(store: Arc<Store>) -> Result<impl warp::Reply, Infallible> {
+    Ok(warp::reply::json(&store.snapshot().errors))
 }
 
-pub async fn balances(ledger: Arc<RwLock<Ledger>>) -> Result<impl warp::Reply, Infallible> {
-    let ledger = ledger.read().await;
+pub async fn balances(store: Arc<Store>) -> Result<impl warp::Reply, Infallible> {
+    let snapshot = store.snapshot();
+    let ledger = &snapshot.ledger;
     Ok(warp::reply::json(&balance_sheet_to_list(
         ledger.balance_sheet(),
     )))
 }
 
-fn filter_account(txn: &Transaction, account: &str) -> bool {
-    for posting in txn.postings() {
-        if posting.account.starts_with(account) {
-            return true;
+fn meta_to_pairs(meta: &Meta) -> Vec<(String, String)> {
+    let mut pairs: Vec<_> = meta
+        .iter()
+        .map(|(key, (value, _))| (key.to_string(), value.to_string()))
+        .collect();
+    pairs.sort();
+    pairs
+}
+
+fn subtree_units(ledger: &Ledger, root: &str) -> HashMap<String, Decimal> {
+    let mut units: HashMap<String, Decimal> = HashMap::new();
+    for (account, account_map) in ledger.balance_sheet() {
+        if !is_sub_account(account, root) {
+            continue;
+        }
+        for (currency, cost_map) in account_map {
+            let number: Decimal = cost_map.values().sum();
+            if !number.is_zero() {
+                *units.entry(currency.to_string()).or_default() += number;
+            }
+        }
+    }
+    units.retain(|_, number| !number.is_zero());
+    units
+}
+
+fn account_exists(ledger: &Ledger, name: &str) -> bool {
+    ledger
+        .accounts()
+        .keys()
+        .any(|account| is_sub_account(account, name))
+}
+
+pub fn build_account_overview(ledger: &Ledger, name: &str) -> Option<AccountOverview> {
+    if !account_exists(ledger, name) {
+        return None;
+    }
+    let mut overview = AccountOverview {
+        name: name.to_string(),
+        balance: subtree_units(ledger, name),
+        ..Default::default()
+    };
+    if let Some(info) = ledger.accounts().get(name) {
+        overview.open = Some(info.open().0);
+        overview.close = info.close().as_ref().map(|(date, _)| *date);
+        let mut currencies: Vec<_> = info.currencies().iter().cloned().collect();
+        currencies.sort();
+        overview.currencies = currencies;
+        overview.meta = meta_to_pairs(info.meta());
+    }
+    let mut children: Vec<&str> = ledger
+        .accounts()
+        .keys()
+        .filter(|account| account.len() > name.len() && is_sub_account(account, name))
+        .filter_map(|account| account[name.len() + 1..].split(':').next())
+        .collect();
+    children.sort_unstable();
+    children.dedup();
+    overview.children = children
+        .into_iter()
+        .map(|child| {
+            let child_name = format!("{}:{}", name, child);
+            let balance = subtree_units(ledger, &child_name);
+            AccountBalance {
+                name: child_name,
+                balance,
+            }
+        })
+        .collect();
+    for txn in ledger.txns() {
+        let postings: Vec<_> = txn
+            .postings()
+            .iter()
+            .filter(|posting| is_sub_account(&posting.account, name))
+            .collect();
+        if postings.is_empty() {
+            continue;
+        }
+        if txn.flag() == TxnFlag::Balance {
+            if overview
+                .last_balance
+                .as_ref()
+                .map_or(true, |assertion| assertion.date <= txn.date())
+            {
+                overview.last_balance = Some(BalanceAssertion {
+                    date: txn.date(),
+                    amounts: postings.iter().map(|p| p.amount.clone()).collect(),
+                });
+            }
+        } else if overview.last_txn.map_or(true, |date| date <= txn.date()) {
+            overview.last_txn = Some(txn.date());
         }
     }
-    false
+    Some(overview)
 }
 
+pub async fn account_info(name: String, store: Arc<Store>) -> Result<impl warp::Reply, Rejection> {
+    let snapshot = store.snapshot();
+    let ledger = &snapshot.ledger;
+    match build_account_overview(ledger, &name) {
+        Some(overview) => Ok(warp::reply::json(&overview)),
+        None => Err(rejection::not_found(format!("Unknown account {}", name))),
+    }
+}
+
+pub fn build_account_list(ledger: &Ledger) -> Vec<AccountSummary> {
+    let mut postings: HashMap<&str, usize> = HashMap::new();
+    for txn in ledger.txns() {
+        if txn.flag() == TxnFlag::Balance {
+            continue;
+        }
+        for posting in txn.postings() {
+            *postings.entry(&posting.account).or_default() += 1;
+        }
+    }
+    let mut accounts: Vec<_> = ledger
+        .accounts()
+        .iter()
+        .map(|(account, info)| AccountSummary {
+            name: account.to_string(),
+            open: info.open().0,
+            close: info.close().as_ref().map(|(date, _)| *date),
+            postings: postings.get(&**account).copied().unwrap_or(0),
+        })
+        .collect();
+    accounts.sort_by(|a, b| a.name.cmp(&b.name));
+    accounts
+}
+
+pub async fn accounts(store: Arc<Store>) -> Result<impl warp::Reply, Infallible> {
+    let snapshot = store.snapshot();
+    let ledger = &snapshot.ledger;
+    Ok(warp::reply::json(&build_account_list(ledger)))
+}
+
+pub async fn budget(
+    options: BudgetOptions,
+    store: Arc<Store>,
+) -> Result<impl warp::Reply, Rejection> {
+    let period = match options.time.as_deref() {
+        Some(time) => ReportPeriod::parse(time)
+            .ok_or_else(|| rejection::bad_request(format!("Invalid period {}", time)))?,
+        None => ReportPeriod::current(),
+    };
+    let snapshot = store.snapshot();
+    Ok(warp::reply::json(&budget::budget_report(
+        &snapshot.ledger,
+        &snapshot.budgets,
+        period,
+    )))
+}
+
+fn filter_account(txn: &Transaction, account: &str) -> bool {
+    txn.postings()
+        .iter()
+        .any(|posting| is_sub_account(&posting.account, account))
+}
+
+type Inventory<'t> = HashMap<&'t str, HashMap<Option<&'t UnitCost>, Decimal>>;
+
 fn update_balance<'t>(
     txn: &'t Transaction,
     account: &str,
-    running_balance: &mut HashMap<&'t str, Decimal>,
+    inventory: &mut Inventory<'t>,
 ) -> HashMap<&'t str, Decimal> {
     if txn.flag() == TxnFlag::Balance {
         return HashMap::new();
     }
     let mut changes: HashMap<&str, Decimal> = HashMap::new();
     for posting in txn.postings().iter() {
-        if posting.cost.is_none() && posting.account.starts_with(&account) {
-            *changes.entry(posting.amount.currency.as_str()).or_default() += posting.amount.number;
+        if is_sub_account(&posting.account, account) {
+            let currency = posting.amount.currency.as_str();
+            *changes.entry(currency).or_default() += posting.amount.number;
+            *inventory
+                .entry(currency)
+                .or_default()
+                .entry(posting.cost.as_ref())
+                .or_default() += posting.amount.number;
         }
     }
-    for (c, n) in changes.iter() {
-        *running_balance.entry(c).or_default() += n;
-    }
     changes
 }
 
-pub async fn account_journal(
-    account: Option<String>,
-    options: FilterOptions,
-    ledger: Arc<RwLock<Ledger>>,
-) -> Result<impl warp::Reply, Infallible> {
-    let ledger = ledger.read().await;
-    let mut filters: Vec<Box<dyn Fn(&Transaction) -> bool>> = Vec::new();
-    if let Some(ref account) = account {
+fn inventory_units<'t>(inventory: &Inventory<'t>) -> HashMap<&'t str, Decimal> {
+    inventory
+        .iter()
+        .map(|(currency, lots)| (*currency, lots.values().sum()))
+        .collect()
+}
+
+fn inventory_book_value<'t>(inventory: &Inventory<'t>) -> HashMap<&'t str, Decimal> {
+    let mut book_value: HashMap<&str, Decimal> = HashMap::new();
+    for (currency, lots) in inventory {
+        for (cost, number) in lots {
+            if let Some(unit_cost) = *cost {
+                *book_value
+                    .entry(unit_cost.amount.currency.as_str())
+                    .or_default() += unit_cost.amount.number * number;
+            } else {
+                *book_value.entry(*currency).or_default() += number;
+            }
+        }
+    }
+    book_value
+}
+
+fn inventory_lots(inventory: &Inventory) -> Vec<Position> {
+    let mut lots: Vec<Position> = inventory
+        .iter()
+        .flat_map(|(currency, lots)| {
+            lots.iter()
+                .filter(|(_, number)| !number.is_zero())
+                .map(move |(cost, number)| Position {
+                    currency: (*currency).into(),
+                    number: *number,
+                    cost: (*cost).cloned(),
+                })
+        })
+        .collect();
+    // The inventory is a hash map, so every field takes part to keep the
+    // order stable between requests.
+    lots.sort_by_cached_key(|lot| {
+        let cost = lot.cost.as_ref();
+        (
+            lot.currency.to_string(),
+            cost.map(|c| c.date),
+            cost.map(|c| c.amount.currency.to_string()),
+            cost.map(|c| c.amount.number),
+            lot.number,
+        )
+    });
+    lots
+}
+
+// Comma separated flags as in the ledger file: `*`, `!`, `pad` or `balance`.
+pub fn parse_flags(flags: &str) -> Result<Vec<TxnFlag>, String> {
+    flags
+        .split(',')
+        .map(|flag| match flag.trim() {
+            "*" => Ok(TxnFlag::Posted),
+            "!" => Ok(TxnFlag::Pending),
+            "pad" => Ok(TxnFlag::Pad),
+            "balance" => Ok(TxnFlag::Balance),
+            other => Err(format!("Invalid flag {}", other)),
+        })
+        .collect()
+}
+
+pub type Journal<'s> = Vec<JournalItem<&'s str, &'s Transaction>>;
+
+pub fn build_journal<'s>(
+    ledger: &'s Ledger,
+    account: Option<&str>,
+    options: &FilterOptions,
+    changed: Option<&HashSet<String>>,
+) -> Result<(Journal<'s>, usize), String> {
+    let flags = options.flags.as_deref().map(parse_flags).transpose()?;
+    let exclude_flags = options
+        .exclude_flags
+        .as_deref()
+        .map(parse_flags)
+        .transpose()?;
+    let mut filters: Vec<Box<dyn Fn(&Transaction) -> bool + '_>> = Vec::new();
+    if let Some(changed) = changed {
+        filters.push(Box::new(move |txn: &Transaction| {
+            changed.contains(&changes::fingerprint(txn))
+        }));
+    }
+    if let Some(account) = account {
         filters.push(Box::new(move |txn: &Transaction| {
             filter_account(txn, account)
         }));
@@ -208,11 +748,38 @@ pub async fn account_journal(
     };
     if let Some(time) = &options.time {
         if let Ok(year) = time.parse::<i32>() {
-            filters.push(Box::new(move |txn: &Transaction| {
-                txn.date().year() == year
-            }));
+            filters.push(Box::new(move |txn: &Transaction| txn.date().year() == year));
         }
     }
+    if let Some(flags) = flags {
+        filters.push(Box::new(move |txn: &Transaction| {
+            flags.contains(&txn.flag())
+        }));
+    }
+    if let Some(flags) = exclude_flags {
+        filters.push(Box::new(move |txn: &Transaction| {
+            !flags.contains(&txn.flag())
+        }));
+    }
+    if options.currency.is_some() || options.min_amount.is_some() || options.max_amount.is_some() {
+        let posting_account = if options.account_postings == Some(true) {
+            account.or(options.account.as_deref())
+        } else {
+            None
+        };
+        filters.push(Box::new(move |txn: &Transaction| {
+            txn.postings().iter().any(|posting| {
+                let number = posting.amount.number.abs();
+                posting_account.map_or(true, |a| is_sub_account(&posting.account, a))
+                    && options
+                        .currency
+                        .as_deref()
+                        .map_or(true, |c| posting.amount.currency.as_str() == c)
+                    && options.min_amount.map_or(true, |min| number >= min)
+                    && options.max_amount.map_or(true, |max| number <= max)
+            })
+        }));
+    }
     let txns: Vec<_> = ledger
         .txns()
         .iter()
@@ -230,10 +797,7 @@ pub async fn account_journal(
     let entries = std::cmp::max(options.entries.unwrap_or(50), 1);
     let old_first = options.old_first.unwrap_or(false);
     if (page - 1) * entries >= txns.len() {
-        Ok(warp::reply::json(&(
-            Vec::<Transaction>::new(),
-            total_number,
-        )))
+        Ok((Vec::new(), total_number))
     } else {
         let num_skip = if old_first {
             (page - 1) * entries
@@ -242,41 +806,263 @@ pub async fn account_journal(
         } else {
             txns.len() - page * entries
         };
-        let mut running_balance: HashMap<&str, Decimal> = HashMap::new();
-        if let Some(ref account) = account {
-            for txn in txns.iter().take(num_skip) {
-                let _ = update_balance(txn, account, &mut running_balance);
-            }
-        }
         let num_take = if old_first {
             std::cmp::min(entries, txns.len() - entries * (page - 1))
         } else {
             (txns.len() - entries * (page - 1)) - num_skip
         };
-        let mut items: Vec<_> = txns
-            .into_iter()
-            .skip(num_skip)
-            .take(num_take)
-            .map(|txn| {
-                if let Some(ref account) = account {
-                    let changes = update_balance(txn, account, &mut running_balance);
-                    JournalItem {
-                        txn,
-                        balance: running_balance.clone(),
-                        changes,
+        let page_txns = &txns[num_skip..num_skip + num_take];
+        let mut items: Vec<_> = match account {
+            // The running balance is that of the account, so it covers the
+            // transactions that the other filters hide as well.
+            Some(account) => {
+                let show_lots = options.show_lots.unwrap_or(false);
+                let mut inventory: Inventory = HashMap::new();
+                let mut page_txns = page_txns.iter().peekable();
+                let mut items = Vec::new();
+                for txn in ledger
+                    .txns()
+                    .iter()
+                    .filter(|txn| filter_account(txn, account))
+                {
+                    let next = match page_txns.peek() {
+                        Some(next) => **next,
+                        None => break,
+                    };
+                    let changes = update_balance(txn, account, &mut inventory);
+                    if !std::ptr::eq(txn, next) {
+                        continue;
                     }
-                } else {
-                    JournalItem {
+                    page_txns.next();
+                    let (lots, book_value) = if show_lots {
+                        (inventory_lots(&inventory), inventory_book_value(&inventory))
+                    } else {
+                        (Vec::new(), HashMap::new())
+                    };
+                    items.push(JournalItem {
                         txn,
-                        balance: HashMap::new(),
-                        changes: HashMap::new(),
-                    }
+                        balance: inventory_units(&inventory),
+                        changes,
+                        lots,
+                        book_value,
+                    });
                 }
-            })
-            .collect();
+                items
+            }
+            None => page_txns
+                .iter()
+                .map(|txn| JournalItem {
+                    txn: *txn,
+                    balance: HashMap::new(),
+                    changes: HashMap::new(),
+                    lots: Vec::new(),
+                    book_value: HashMap::new(),
+                })
+                .collect(),
+        };
         if !old_first {
             items.reverse();
         }
-        Ok(warp::reply::json(&(items, total_number)))
+        Ok((items, total_number))
+    }
+}
+
+pub async fn account_journal(
+    account: Option<String>,
+    options: FilterOptions,
+    store: Arc<Store>,
+) -> Result<impl warp::Reply, Rejection> {
+    if let Some(time) = &options.time {
+        if time.parse::<i32>().is_err() {
+            return Err(rejection::bad_request(format!("Invalid year {}", time)));
+        }
+    }
+    let snapshot = store.snapshot();
+    let ledger = &snapshot.ledger;
+    if let Some(name) = &account {
+        if !account_exists(ledger, name) {
+            return Err(rejection::not_found(format!("Unknown account {}", name)));
+        }
+    }
+    let changed = options.changed_since.map(|since| {
+        snapshot
+            .history
+            .iter()
+            .filter(|changes| changes.generation > since)
+            .flat_map(|changes| {
+                changes
+                    .added
+                    .iter()
+                    .chain(changes.modified.iter().map(|m| &m.after))
+            })
+            .map(changes::fingerprint)
+            .collect::<HashSet<_>>()
+    });
+    let journal = build_journal(ledger, account.as_deref(), &options, changed.as_ref())
+        .map_err(rejection::bad_request)?;
+    Ok(warp::reply::json(&journal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use warp::Reply;
+
+    fn view(path: &str, query: &str) -> View {
+        View {
+            name: "View".to_string(),
+            path: path.to_string(),
+            query: query.to_string(),
+        }
+    }
+
+    #[test]
+    fn accepts_views_of_pages_with_their_options() {
+        for (path, query) in [
+            ("/journal", "flags=!&currency=USD&page=2"),
+            ("/account/Assets:Cash", "show_lots=true"),
+            ("/balance_sheet", "show_closed=true"),
+            ("/income", ""),
+            ("/budget", "time=2021-Q1"),
+            ("/holdings", ""),
+            ("/commodity/USD", ""),
+        ] {
+            assert_eq!(check_view(&view(path, query)), Ok(()), "{}?{}", path, query);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_pages_and_options() {
+        for (path, query) in [
+            ("journal", ""),
+            ("/api/journal", ""),
+            ("/account/", ""),
+            ("/settings", ""),
+            ("/journal", "page=first"),
+            ("/journal", "show_closed=true"),
+            ("/budget", "flags=!"),
+            ("/holdings", "page=2"),
+        ] {
+            assert!(
+                check_view(&view(path, query)).is_err(),
+                "{}?{}",
+                path,
+                query
+            );
+        }
+    }
+
+    #[test]
+    fn account_journals_keep_the_balance_of_hidden_transactions() {
+        let dir = std::env::temp_dir().join(format!(
+            "lumi-server-handlers-{}-journal",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.beancount");
+        std::fs::write(
+            &path,
+            r#"2021-01-01 open Assets:Cash USD
+2021-01-01 open Assets:Cash:Coins USD
+2021-01-01 open Assets:Cashbox USD
+2021-01-01 open Income:Salary USD
+
+2021-01-02 ! "Pending"
+  Assets:Cash 100 USD
+  Income:Salary
+
+2021-01-03 * "Box"
+  Assets:Cashbox 7 USD
+  Income:Salary
+
+2021-01-04 * "Coins"
+  Assets:Cash:Coins 5 USD
+  Income:Salary
+"#,
+        )
+        .unwrap();
+        let (ledger, _) = Ledger::from_file(path.to_str().unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+        let options = FilterOptions {
+            flags: Some("*".to_string()),
+            ..FilterOptions::default()
+        };
+        let (journal, total) = build_journal(&ledger, Some("Assets:Cash"), &options, None).unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(journal[0].txn.narration(), "Coins");
+        assert_eq!(journal[0].changes["USD"], Decimal::from(5));
+        assert_eq!(journal[0].balance["USD"], Decimal::from(105));
+    }
+
+    const UPLOAD_LEDGER: &str = r#"2021-01-01 open Assets:Cash USD
+2021-01-01 open Expenses:Food USD
+
+2021-01-02 * "Shop" "Lunch"
+  Assets:Cash -10 USD
+  Expenses:Food 10 USD
+"#;
+
+    async fn uploaded(reply: Result<impl warp::Reply, Rejection>) -> UploadedDocument {
+        let body = reply.unwrap().into_response().into_body();
+        serde_json::from_slice(&warp::hyper::body::to_bytes(body).await.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn concurrent_uploads_keep_both_edits() {
+        let dir = std::env::temp_dir().join(format!(
+            "lumi-server-handlers-{}-uploads",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.beancount");
+        std::fs::write(&path, UPLOAD_LEDGER).unwrap();
+        let path = path.to_str().unwrap();
+        let store = Arc::new(Store::open(path, None, Arc::new(Metrics::new())).unwrap());
+        let documents = Arc::new(Documents::new(path, &[]));
+        let snapshot = store.snapshot();
+        let first_posting = &snapshot.ledger.txns()[0].postings()[0];
+        let date = NaiveDate::from_ymd_opt(2021, 1, 2).unwrap();
+        let upload = |filename: &str, src: Option<(String, usize)>| {
+            let options = UploadOptions {
+                account: "Assets:Cash".to_string(),
+                date,
+                filename: filename.to_string(),
+                src_file: src.as_ref().map(|(file, _)| file.clone()),
+                src_line: src.map(|(_, line)| line),
+            };
+            upload_document(
+                options,
+                Some("application/pdf".to_string()),
+                Bytes::from_static(b"%PDF-1.4"),
+                store.clone(),
+                documents.clone(),
+            )
+        };
+        let src = (
+            first_posting.src.file.to_string(),
+            first_posting.src.start.line,
+        );
+        let (attached, appended) =
+            tokio::join!(upload("lunch.pdf", Some(src)), upload("shop.pdf", None));
+        let (attached, appended) = (uploaded(attached).await, uploaded(appended).await);
+        assert_eq!(attached.document.accounts, ["Assets:Cash", "Expenses:Food"]);
+        assert_eq!(attached.document.description.as_deref(), Some("Shop Lunch"));
+        assert_eq!(appended.document.accounts, ["Assets:Cash"]);
+        assert_eq!(appended.document.description, None);
+
+        let content = std::fs::read_to_string(path).unwrap();
+        assert!(content
+            .contains("  document: \"2021-01-02.Assets.Cash.lunch.pdf\"\n  Assets:Cash -10 USD"));
+        assert!(
+            content.contains("2021-01-02 document Assets:Cash \"2021-01-02.Assets.Cash.shop.pdf\"")
+        );
+        let snapshot = store.snapshot();
+        assert_eq!(
+            documents::list(&snapshot.ledger, &snapshot.directives, None).len(),
+            2
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
     }
 }
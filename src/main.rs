@@ -1,83 +1,164 @@
-use clap::{clap_app, App};
-use headers::{ContentType, HeaderMapExt};
-use include_dir::{include_dir, Dir};
-use lumi::Ledger;
-use std::collections::HashSet;
+use clap::{clap_app, App, Arg, ArgMatches, SubCommand};
+use documents::Documents;
+use lumi_server_defs::ServerOptions;
+use metrics::Metrics;
+use snapshot::Store;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::Arc;
 use tokio::signal;
-use tokio::sync::{oneshot, RwLock};
+use tokio::sync::oneshot;
+use views::Views;
 use warp::Filter;
 
+mod assets;
+mod budget;
+mod changes;
+mod cli;
+mod commodities;
+mod complete;
+mod compression;
+mod directives;
+mod documents;
+#[cfg(feature = "web-ui")]
+mod embedded;
 mod filters;
 mod handlers;
+mod metrics;
+mod openapi;
+mod rejection;
+mod snapshot;
+mod views;
 
-static WEB_DIR: Dir = include_dir!("lumi-web/dist");
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const AUTHOR: &str = env!("CARGO_PKG_AUTHORS");
 
-fn get_file(path: &str) -> Option<&'static [u8]> {
-    WEB_DIR.get_file(path).map(|f| f.contents)
+// The top level takes them too, as `serve` is the default subcommand.
+fn serve_args() -> Vec<Arg<'static, 'static>> {
+    vec![
+        Arg::with_name("INPUT").required(true).help("Input file"),
+        Arg::with_name("ADDR")
+            .short("a")
+            .long("addr")
+            .takes_value(true)
+            .help("Bind address"),
+        Arg::with_name("BUDGET")
+            .short("b")
+            .long("budget")
+            .takes_value(true)
+            .help("Extra budget file with custom \"budget\" entries"),
+        Arg::with_name("READ_ONLY")
+            .long("read-only")
+            .help("Disables all endpoints that modify the ledger"),
+        Arg::with_name("WEB_DIR")
+            .long("web-dir")
+            .takes_value(true)
+            .help("Serves the web UI from a directory instead of the embedded copy"),
+        Arg::with_name("DOCUMENTS")
+            .long("documents")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Directory with documents of the ledger, may be repeated [default: the directory of the input file]"),
+    ]
 }
 
-const VERSION: &str = env!("CARGO_PKG_VERSION");
-const AUTHOR: &str = env!("CARGO_PKG_AUTHORS");
+fn is_positive(value: String) -> Result<(), String> {
+    match value.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(()),
+        _ => Err(format!("{} is not a positive number", value)),
+    }
+}
 
 #[tokio::main]
 async fn main() -> std::io::Result<()> {
     pretty_env_logger::init();
-    let root_index = warp::path::end().map(|| {
-        let index = get_file("index.html").unwrap();
-        warp::reply::html(index)
-    });
-
-    let pages: HashSet<&str> = [
-        "errors",
-        "holdings",
-        "account",
-        "journal",
-        "income",
-        "balance_sheet",
-    ]
-    .into_iter()
-    .collect();
-    let file = warp::path::param().map(move |path: String| {
-        if let Some(contents) = get_file(&path) {
-            let mime = mime_guess::from_path(&path).first_or_octet_stream();
-            let mut resp = warp::reply::Response::new(contents.into());
-            resp.headers_mut().typed_insert(ContentType::from(mime));
-            resp
-        } else if pages.contains(path.as_str()) {
-            let index = get_file("index.html").unwrap();
-            let mut resp = warp::reply::Response::new(index.into());
-            resp.headers_mut().typed_insert(ContentType::html());
-            resp
-        } else {
-            let mut resp = warp::reply::Response::default();
-            *resp.status_mut() = warp::http::StatusCode::NOT_FOUND;
-            resp
-        }
-    });
-    let get_file = warp::get().and(root_index.or(file));
-
-    let matches = clap_app!(@app(App::new("lumi-server"))
+    let serve_args = serve_args();
+    let matches = clap_app!(@app(App::new("lumi-server")
+            .args(&serve_args)
+            .subcommand(SubCommand::with_name("serve")
+                .about("Serves the web UI and the JSON API (default)")
+                .args(&serve_args)))
         (version: VERSION)
         (author: AUTHOR)
-        (@arg INPUT: +required "Input file")
-        (@arg ADDR: -a --("addr") +takes_value "Bind address" )
+        (@setting SubcommandsNegateReqs)
+        (@subcommand ("export-static") =>
+            (about: "Writes the web UI and pre-rendered API responses to a directory")
+            (@arg INPUT: +required "Input file")
+            (@arg DIR: +required "Output directory")
+            (@arg BUDGET: -b --("budget") +takes_value "Extra budget file with custom \"budget\" entries")
+        )
+        (@subcommand check =>
+            (about: "Prints ledger errors and exits non-zero if there are any")
+            (@arg INPUT: +required "Input file")
+            (@arg JSON: --json "Prints JSON instead of text")
+        )
+        (@subcommand balance =>
+            (about: "Prints the balances of all accounts under a root account")
+            (@arg INPUT: +required "Input file")
+            (@arg ROOT: +required "Root account, e.g. Assets")
+            (@arg SHOW_CLOSED: --("show-closed") "Includes closed accounts")
+            (@arg JSON: --json "Prints JSON instead of text")
+        )
+        (@subcommand journal =>
+            (about: "Prints the journal of an account, or of all accounts")
+            (@arg INPUT: +required "Input file")
+            (@arg ACCOUNT: "Account name")
+            (@arg TIME: -t --time +takes_value "Only transactions of the given year")
+            (@arg ENTRIES: -n --entries +takes_value {is_positive} "Transactions per page")
+            (@arg PAGE: -p --page +takes_value {is_positive} "Page number")
+            (@arg OLD_FIRST: --("old-first") "Lists older transactions first")
+            (@arg LOTS: --lots "Includes lots and book value in JSON output")
+            (@arg POSTINGS: --postings "Prints postings below each transaction")
+            (@arg JSON: --json "Prints JSON instead of text")
+        )
     )
     .get_matches();
+    let success = match matches.subcommand() {
+        ("check", Some(sub_matches)) => cli::check(sub_matches),
+        ("balance", Some(sub_matches)) => cli::balance(sub_matches),
+        ("journal", Some(sub_matches)) => cli::journal(sub_matches),
+        ("export-static", Some(sub_matches)) => cli::export_static(sub_matches),
+        ("serve", Some(sub_matches)) => return serve(sub_matches).await,
+        _ => return serve(&matches).await,
+    };
+    if !success {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+async fn serve(matches: &ArgMatches<'_>) -> std::io::Result<()> {
     let path = matches.value_of("INPUT").unwrap();
     let addr: SocketAddr = matches
         .value_of("ADDR")
         .and_then(|addr| addr.parse().ok())
         .unwrap_or_else(|| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 3000));
-    let (ledger, errors) = Ledger::from_file(path);
-    let api = filters::ledger_api(
-        Arc::new(RwLock::new(ledger)),
-        Arc::new(RwLock::new(errors)),
-        path,
-    );
+    let budget_path = matches.value_of("BUDGET");
+    let metrics = Arc::new(Metrics::new());
+    let store = Arc::new(Store::open(path, budget_path, metrics.clone())?);
+    let options = ServerOptions {
+        read_only: matches.is_present("READ_ONLY"),
+    };
+    let views = Arc::new(Views::open(path));
+    let document_roots: Vec<&str> = matches
+        .values_of("DOCUMENTS")
+        .map(|values| values.collect())
+        .unwrap_or_default();
+    let documents = Arc::new(Documents::new(path, &document_roots));
+    let api = filters::ledger_api(store, views, documents, metrics.clone(), options)
+        .and(warp::header::optional::<String>("accept-encoding"))
+        .and_then(|reply, accept_encoding: Option<String>| {
+            compression::compress_reply(reply, accept_encoding)
+        });
 
-    let routes = api.or(get_file).with(warp::log("lumi-server"));
+    let request_metrics = metrics.clone();
+    let routes = api
+        .or(filters::monitoring(metrics))
+        .or(assets::routes(matches.value_of("WEB_DIR")))
+        .with(warp::log("lumi-server"))
+        .with(warp::log::custom(move |info| {
+            request_metrics.observe_request(info)
+        }));
     let (tx, rx) = oneshot::channel();
     let (_addr, server) = warp::serve(routes).bind_with_graceful_shutdown(addr, async {
         rx.await.ok();
@@ -0,0 +1,225 @@
+use crate::filters::API_ROUTES;
+use lumi::{Error, ErrorLevel, Ledger};
+use lumi_server_defs::Health;
+use prometheus::{
+    Encoder, Gauge, HistogramOpts, HistogramVec, IntCounter, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use std::time::Duration;
+
+pub struct Metrics {
+    registry: Registry,
+    load_duration: Gauge,
+    transactions: IntGauge,
+    accounts: IntGauge,
+    errors: IntGaugeVec,
+    refreshes: IntCounter,
+    last_reload: IntGauge,
+    last_reload_success: IntGauge,
+    requests: HistogramVec,
+}
+
+fn level_label(level: &ErrorLevel) -> &'static str {
+    match level {
+        ErrorLevel::Info => "info",
+        ErrorLevel::Warning => "warning",
+        ErrorLevel::Error => "error",
+    }
+}
+
+// Labels come from a fixed set, so that clients cannot add label values by
+// requesting made-up paths.
+fn route_label(path: &str, status: u16) -> &'static str {
+    if status == 404 {
+        return "not_found";
+    }
+    let path = path.trim_end_matches('/');
+    let segments: Vec<&str> = path.split('/').collect();
+    let matches = |route: &str| {
+        let route: Vec<&str> = route.split('/').collect();
+        route.len() == segments.len()
+            && route
+                .iter()
+                .zip(&segments)
+                .all(|(r, s)| r.starts_with(':') || r == s)
+    };
+    match segments.as_slice() {
+        ["", "api", ..] => API_ROUTES
+            .iter()
+            .map(|(_, route)| *route)
+            .find(|route| matches(route))
+            .unwrap_or("other"),
+        ["", "metrics"] => "/metrics",
+        ["", "healthz"] => "/healthz",
+        _ => "static",
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        let registry = Registry::new_custom(Some("lumi".to_string()), None).unwrap();
+        let load_duration = Gauge::new(
+            "ledger_load_duration_seconds",
+            "Time spent loading the ledger file",
+        )
+        .unwrap();
+        let transactions = IntGauge::new(
+            "ledger_transactions",
+            "Number of transactions in the ledger",
+        )
+        .unwrap();
+        let accounts =
+            IntGauge::new("ledger_accounts", "Number of accounts in the ledger").unwrap();
+        let errors = IntGaugeVec::new(
+            Opts::new("ledger_errors", "Number of ledger errors by level"),
+            &["level"],
+        )
+        .unwrap();
+        let refreshes = IntCounter::new("refreshes_total", "Number of ledger refreshes").unwrap();
+        let last_reload = IntGauge::new(
+            "last_reload_timestamp_seconds",
+            "Unix time of the last ledger load",
+        )
+        .unwrap();
+        let last_reload_success = IntGauge::new(
+            "last_reload_success",
+            "Whether the last ledger load produced a ledger",
+        )
+        .unwrap();
+        let requests = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "HTTP request latency by route",
+            ),
+            &["route", "status"],
+        )
+        .unwrap();
+        registry.register(Box::new(load_duration.clone())).unwrap();
+        registry.register(Box::new(transactions.clone())).unwrap();
+        registry.register(Box::new(accounts.clone())).unwrap();
+        registry.register(Box::new(errors.clone())).unwrap();
+        registry.register(Box::new(refreshes.clone())).unwrap();
+        registry.register(Box::new(last_reload.clone())).unwrap();
+        registry
+            .register(Box::new(last_reload_success.clone()))
+            .unwrap();
+        registry.register(Box::new(requests.clone())).unwrap();
+        Metrics {
+            registry,
+            load_duration,
+            transactions,
+            accounts,
+            errors,
+            refreshes,
+            last_reload,
+            last_reload_success,
+            requests,
+        }
+    }
+
+    pub fn record_load(&self, ledger: &Ledger, errors: &[Error], duration: Duration) {
+        self.load_duration.set(duration.as_secs_f64());
+        self.transactions.set(ledger.txns().len() as i64);
+        self.accounts.set(ledger.accounts().len() as i64);
+        for level in [ErrorLevel::Info, ErrorLevel::Warning, ErrorLevel::Error] {
+            let count = errors.iter().filter(|e| e.level == level).count();
+            self.errors
+                .with_label_values(&[level_label(&level)])
+                .set(count as i64);
+        }
+        // Errors in the ledger, like a failing balance assertion, are the
+        // user's bookkeeping and counted in `ledger_errors`; the load worked.
+        self.last_reload.set(chrono::Utc::now().timestamp());
+        self.last_reload_success.set(1);
+    }
+
+    // A load that did not produce a ledger at all, e.g. a missing file.
+    pub fn record_failed_load(&self) {
+        self.last_reload.set(chrono::Utc::now().timestamp());
+        self.last_reload_success.set(0);
+    }
+
+    pub fn record_refresh(&self) {
+        self.refreshes.inc();
+    }
+
+    pub fn observe_request(&self, info: warp::log::Info) {
+        let status = info.status();
+        self.requests
+            .with_label_values(&[route_label(info.path(), status.as_u16()), status.as_str()])
+            .observe(info.elapsed().as_secs_f64());
+    }
+
+    pub fn health(&self) -> Health {
+        Health {
+            ok: self.last_reload_success.get() == 1,
+            last_reload: self.last_reload.get(),
+            errors: self
+                .errors
+                .with_label_values(&[level_label(&ErrorLevel::Error)])
+                .get() as usize,
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openapi;
+
+    #[test]
+    fn labels_every_documented_route() {
+        let document = openapi::document();
+        for path in document["paths"].as_object().unwrap().keys() {
+            let path = path.replace('{', "").replace('}', "");
+            assert_ne!(route_label(&path, 200), "other", "{}", path);
+        }
+    }
+
+    #[test]
+    fn labels_unknown_routes_as_other() {
+        assert_eq!(route_label("/api/random", 405), "other");
+        assert_eq!(route_label("/api/views/extra", 400), "other");
+        assert_eq!(route_label("/api/anything", 404), "not_found");
+        assert_eq!(
+            route_label("/api/account/Assets:Cash", 200),
+            "/api/account/:name"
+        );
+    }
+
+    #[test]
+    fn ledger_errors_do_not_fail_the_health_check() {
+        let dir =
+            std::env::temp_dir().join(format!("lumi-server-metrics-{}-health", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.beancount");
+        std::fs::write(
+            &path,
+            "2021-01-01 open Assets:Cash USD\n2021-01-02 balance Assets:Cash 5 USD\n",
+        )
+        .unwrap();
+        let (ledger, errors) = Ledger::from_file(path.to_str().unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+        let metrics = Metrics::new();
+        metrics.record_load(&ledger, &errors, Duration::from_millis(1));
+        let health = metrics.health();
+        assert!(health.ok);
+        assert!(health.errors > 0);
+        metrics.record_failed_load();
+        assert!(!metrics.health().ok);
+    }
+}
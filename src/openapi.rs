@@ -0,0 +1,1282 @@
+use serde_json::{json, Map, Value};
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn array_of(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+fn map_of(values: Value) -> Value {
+    json!({ "type": "object", "additionalProperties": values })
+}
+
+fn nullable(schema: Value) -> Value {
+    json!({ "allOf": [schema], "nullable": true })
+}
+
+fn decimal() -> Value {
+    json!({ "type": "string", "format": "decimal", "example": "12.34" })
+}
+
+fn date() -> Value {
+    json!({ "type": "string", "format": "date" })
+}
+
+fn object(required: &[&str], properties: Value) -> Value {
+    json!({ "type": "object", "required": required, "properties": properties })
+}
+
+fn schemas() -> Map<String, Value> {
+    let schemas = vec![
+        ("Decimal", decimal()),
+        (
+            "Amount",
+            object(
+                &["number", "currency"],
+                json!({
+                    "number": decimal(),
+                    "currency": { "type": "string" },
+                }),
+            ),
+        ),
+        (
+            "UnitCost",
+            object(
+                &["amount", "date"],
+                json!({
+                    "amount": schema_ref("Amount"),
+                    "date": date(),
+                }),
+            ),
+        ),
+        (
+            "Location",
+            object(
+                &["line", "col"],
+                json!({
+                    "line": { "type": "integer" },
+                    "col": { "type": "integer" },
+                }),
+            ),
+        ),
+        (
+            "Source",
+            object(
+                &["file", "start", "end"],
+                json!({
+                    "file": { "type": "string" },
+                    "start": schema_ref("Location"),
+                    "end": schema_ref("Location"),
+                }),
+            ),
+        ),
+        (
+            "ErrorLevel",
+            json!({ "type": "string", "enum": ["Info", "Warning", "Error"] }),
+        ),
+        (
+            "Error",
+            object(
+                &["msg", "src", "level"],
+                json!({
+                    "msg": { "type": "string" },
+                    "src": schema_ref("Source"),
+                    "level": schema_ref("ErrorLevel"),
+                }),
+            ),
+        ),
+        (
+            "TxnFlag",
+            json!({ "type": "string", "enum": ["Posted", "Pending", "Balance", "Pad"] }),
+        ),
+        ("Meta", map_of(json!({}))),
+        (
+            "Posting",
+            object(
+                &["account", "amount"],
+                json!({
+                    "account": { "type": "string" },
+                    "amount": schema_ref("Amount"),
+                    "cost": nullable(schema_ref("UnitCost")),
+                    "price": nullable(schema_ref("Amount")),
+                    "meta": schema_ref("Meta"),
+                    "src": schema_ref("Source"),
+                }),
+            ),
+        ),
+        (
+            "Transaction",
+            object(
+                &["date", "flag", "payee", "narration", "postings"],
+                json!({
+                    "date": date(),
+                    "flag": schema_ref("TxnFlag"),
+                    "payee": { "type": "string" },
+                    "narration": { "type": "string" },
+                    "tags": array_of(json!({ "type": "string" })),
+                    "links": array_of(json!({ "type": "string" })),
+                    "meta": schema_ref("Meta"),
+                    "postings": array_of(schema_ref("Posting")),
+                    "src": schema_ref("Source"),
+                }),
+            ),
+        ),
+        (
+            "Position",
+            object(
+                &["currency", "number"],
+                json!({
+                    "currency": { "type": "string" },
+                    "number": decimal(),
+                    "cost": nullable(schema_ref("UnitCost")),
+                }),
+            ),
+        ),
+        (
+            "FilterOptions",
+            object(
+                &[],
+                json!({
+                    "entries": { "type": "integer", "minimum": 0 },
+                    "page": { "type": "integer", "minimum": 1 },
+                    "old_first": { "type": "boolean" },
+                    "account": { "type": "string" },
+                    "time": { "type": "string" },
+                    "show_lots": { "type": "boolean" },
+                    "changed_since": { "type": "integer", "format": "int64" },
+                    "flags": { "type": "string", "example": "!" },
+                    "exclude_flags": { "type": "string", "example": "pad,balance" },
+                    "currency": { "type": "string" },
+                    "min_amount": decimal(),
+                    "max_amount": decimal(),
+                    "account_postings": { "type": "boolean" },
+                }),
+            ),
+        ),
+        (
+            "TrieOptions",
+            object(&[], json!({ "show_closed": { "type": "boolean" } })),
+        ),
+        (
+            "BudgetOptions",
+            object(
+                &[],
+                json!({ "time": { "type": "string", "example": "2021-Q1" } }),
+            ),
+        ),
+        (
+            "TrieTableRow",
+            object(
+                &["level", "name", "numbers"],
+                json!({
+                    "level": { "type": "integer" },
+                    "name": { "type": "string" },
+                    "numbers": array_of(json!({ "type": "string" })),
+                }),
+            ),
+        ),
+        (
+            "TrieTable",
+            object(
+                &["rows", "currencies"],
+                json!({
+                    "rows": array_of(schema_ref("TrieTableRow")),
+                    "currencies": array_of(json!({ "type": "string" })),
+                }),
+            ),
+        ),
+        (
+            "JournalItem",
+            object(
+                &["txn", "balance", "changes"],
+                json!({
+                    "txn": schema_ref("Transaction"),
+                    "balance": map_of(decimal()),
+                    "changes": map_of(decimal()),
+                    "lots": array_of(schema_ref("Position")),
+                    "book_value": map_of(decimal()),
+                }),
+            ),
+        ),
+        (
+            "Journal",
+            json!({
+                "description": "A page of journal items followed by the total number of items",
+                "type": "array",
+                "minItems": 2,
+                "maxItems": 2,
+                "items": {
+                    "oneOf": [array_of(schema_ref("JournalItem")), { "type": "integer" }],
+                },
+            }),
+        ),
+        (
+            "RefreshTime",
+            object(
+                &["timestamp"],
+                json!({
+                    "timestamp": { "type": "integer", "format": "int64" },
+                    "generation": { "type": "integer", "format": "int64" },
+                }),
+            ),
+        ),
+        (
+            "AccountBalance",
+            object(
+                &["name", "balance"],
+                json!({
+                    "name": { "type": "string" },
+                    "balance": map_of(decimal()),
+                }),
+            ),
+        ),
+        (
+            "BalanceAssertion",
+            object(
+                &["date", "amounts"],
+                json!({
+                    "date": date(),
+                    "amounts": array_of(schema_ref("Amount")),
+                }),
+            ),
+        ),
+        (
+            "AccountOverview",
+            object(
+                &["name", "currencies", "meta", "balance", "children"],
+                json!({
+                    "name": { "type": "string" },
+                    "open": nullable(date()),
+                    "close": nullable(date()),
+                    "currencies": array_of(json!({ "type": "string" })),
+                    "meta": array_of(json!({
+                        "type": "array",
+                        "minItems": 2,
+                        "maxItems": 2,
+                        "items": { "type": "string" },
+                    })),
+                    "balance": map_of(decimal()),
+                    "children": array_of(schema_ref("AccountBalance")),
+                    "last_txn": nullable(date()),
+                    "last_balance": nullable(schema_ref("BalanceAssertion")),
+                }),
+            ),
+        ),
+        (
+            "AccountSummary",
+            object(
+                &["name", "open", "postings"],
+                json!({
+                    "name": { "type": "string" },
+                    "open": date(),
+                    "close": nullable(date()),
+                    "postings": { "type": "integer" },
+                }),
+            ),
+        ),
+        (
+            "BudgetRow",
+            object(
+                &["account", "currency", "budgeted", "actual", "remaining"],
+                json!({
+                    "account": { "type": "string" },
+                    "currency": { "type": "string" },
+                    "budgeted": decimal(),
+                    "actual": decimal(),
+                    "remaining": decimal(),
+                    "percent_used": nullable(decimal()),
+                }),
+            ),
+        ),
+        (
+            "BudgetReport",
+            object(
+                &["period", "start", "end", "previous", "next", "rows"],
+                json!({
+                    "period": { "type": "string" },
+                    "start": date(),
+                    "end": date(),
+                    "previous": { "type": "string" },
+                    "next": { "type": "string" },
+                    "rows": array_of(schema_ref("BudgetRow")),
+                }),
+            ),
+        ),
+        (
+            "ChangesOptions",
+            object(
+                &[],
+                json!({ "since": { "type": "integer", "format": "int64" } }),
+            ),
+        ),
+        (
+            "ModifiedTxn",
+            object(
+                &["before", "after"],
+                json!({
+                    "before": schema_ref("Transaction"),
+                    "after": schema_ref("Transaction"),
+                }),
+            ),
+        ),
+        (
+            "BalanceChange",
+            object(
+                &["account", "before", "after"],
+                json!({
+                    "account": { "type": "string" },
+                    "before": map_of(decimal()),
+                    "after": map_of(decimal()),
+                }),
+            ),
+        ),
+        (
+            "Changes",
+            object(
+                &[
+                    "generation",
+                    "loaded_at",
+                    "added",
+                    "removed",
+                    "modified",
+                    "accounts",
+                ],
+                json!({
+                    "generation": { "type": "integer", "format": "int64" },
+                    "loaded_at": { "type": "integer", "format": "int64" },
+                    "added": array_of(schema_ref("Transaction")),
+                    "removed": array_of(schema_ref("Transaction")),
+                    "modified": array_of(schema_ref("ModifiedTxn")),
+                    "accounts": array_of(schema_ref("BalanceChange")),
+                }),
+            ),
+        ),
+        (
+            "ChangeLog",
+            object(
+                &["generation", "truncated", "changes"],
+                json!({
+                    "generation": { "type": "integer", "format": "int64" },
+                    "truncated": { "type": "boolean" },
+                    "changes": array_of(schema_ref("Changes")),
+                }),
+            ),
+        ),
+        (
+            "CompleteOptions",
+            object(
+                &["kind"],
+                json!({
+                    "kind": {
+                        "type": "string",
+                        "enum": ["account", "payee", "narration", "tag", "link", "currency"],
+                        "example": "account",
+                    },
+                    "prefix": { "type": "string" },
+                    "limit": { "type": "integer", "minimum": 0, "maximum": 50 },
+                }),
+            ),
+        ),
+        (
+            "Completion",
+            object(
+                &["value", "count"],
+                json!({
+                    "value": { "type": "string" },
+                    "count": { "type": "integer" },
+                    "accounts": array_of(json!({ "type": "string" })),
+                }),
+            ),
+        ),
+        (
+            "ApiError",
+            object(
+                &["code", "message"],
+                json!({
+                    "code": { "type": "integer" },
+                    "message": { "type": "string" },
+                }),
+            ),
+        ),
+        (
+            "View",
+            object(
+                &["name", "path"],
+                json!({
+                    "name": { "type": "string" },
+                    "path": { "type": "string" },
+                    "query": { "type": "string" },
+                }),
+            ),
+        ),
+        (
+            "ViewOptions",
+            object(
+                &["name"],
+                json!({ "name": { "type": "string", "example": "Pending" } }),
+            ),
+        ),
+        (
+            "Document",
+            object(
+                &["date", "path", "accounts"],
+                json!({
+                    "date": date(),
+                    "path": { "type": "string" },
+                    "accounts": array_of(json!({ "type": "string" })),
+                    "description": nullable(json!({ "type": "string" })),
+                }),
+            ),
+        ),
+        (
+            "DocumentOptions",
+            object(&[], json!({ "account": { "type": "string" } })),
+        ),
+        (
+            "DocumentFileOptions",
+            object(
+                &["path"],
+                json!({ "path": { "type": "string", "example": "receipt.pdf" } }),
+            ),
+        ),
+        (
+            "UploadOptions",
+            object(
+                &["account", "date", "filename"],
+                json!({
+                    "account": { "type": "string", "example": "Assets:Cash" },
+                    "date": { "type": "string", "format": "date", "example": "2021-01-02" },
+                    "filename": { "type": "string", "example": "receipt.pdf" },
+                    "src_file": { "type": "string" },
+                    "src_line": { "type": "integer" },
+                }),
+            ),
+        ),
+        (
+            "UploadedDocument",
+            object(
+                &["document", "refresh"],
+                json!({
+                    "document": schema_ref("Document"),
+                    "refresh": schema_ref("RefreshTime"),
+                }),
+            ),
+        ),
+        (
+            "Price",
+            object(
+                &["date", "number", "currency"],
+                json!({
+                    "date": date(),
+                    "number": decimal(),
+                    "currency": { "type": "string" },
+                }),
+            ),
+        ),
+        (
+            "Commodity",
+            object(
+                &["name", "meta", "precision", "units"],
+                json!({
+                    "name": { "type": "string" },
+                    "meta": array_of(json!({
+                        "type": "array",
+                        "minItems": 2,
+                        "maxItems": 2,
+                        "items": { "type": "string" },
+                    })),
+                    "precision": { "type": "integer" },
+                    "units": decimal(),
+                    "latest_price": nullable(schema_ref("Price")),
+                }),
+            ),
+        ),
+        (
+            "CommodityHolding",
+            object(
+                &["account", "units", "book_value", "market_value"],
+                json!({
+                    "account": { "type": "string" },
+                    "units": decimal(),
+                    "book_value": map_of(decimal()),
+                    "market_value": map_of(decimal()),
+                }),
+            ),
+        ),
+        (
+            "CommodityDetails",
+            object(
+                &["commodity", "prices", "holdings"],
+                json!({
+                    "commodity": schema_ref("Commodity"),
+                    "prices": array_of(schema_ref("Price")),
+                    "holdings": array_of(schema_ref("CommodityHolding")),
+                }),
+            ),
+        ),
+        (
+            "ServerOptions",
+            object(
+                &["read_only"],
+                json!({ "read_only": { "type": "boolean" } }),
+            ),
+        ),
+    ];
+    schemas
+        .into_iter()
+        .map(|(name, schema)| (name.to_string(), schema))
+        .collect()
+}
+
+fn path_param(name: &str) -> Value {
+    json!({ "name": name, "in": "path", "required": true, "schema": { "type": "string" } })
+}
+
+// Expands an options struct from the schemas into individual query parameters.
+fn query_params(schemas: &Map<String, Value>, options: &str) -> Vec<Value> {
+    let required = schemas[options]["required"].as_array().unwrap();
+    schemas[options]["properties"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .map(|(name, schema)| {
+            json!({
+                "name": name,
+                "in": "query",
+                "required": required.contains(&json!(name)),
+                "schema": schema,
+            })
+        })
+        .collect()
+}
+
+fn operation(summary: &str, parameters: Vec<Value>, response: Value) -> Value {
+    json!({
+        "summary": summary,
+        "parameters": parameters,
+        "responses": {
+            "200": {
+                "description": "OK",
+                "content": { "application/json": { "schema": response } },
+            },
+            "default": {
+                "description": "Error",
+                "content": { "application/json": { "schema": schema_ref("ApiError") } },
+            },
+        },
+    })
+}
+
+fn with_body(mut operation: Value, schema: &str, example: Value) -> Value {
+    operation["requestBody"] = json!({
+        "required": true,
+        "content": {
+            "application/json": { "schema": schema_ref(schema), "example": example },
+        },
+    });
+    operation
+}
+
+// For uploads, where the file itself is the body.
+fn with_file_body(mut operation: Value, content_types: &[&str], example: &str) -> Value {
+    let content: Map<String, Value> = content_types
+        .iter()
+        .map(|content_type| {
+            (
+                content_type.to_string(),
+                json!({ "schema": { "type": "string", "format": "binary" }, "example": example }),
+            )
+        })
+        .collect();
+    operation["requestBody"] = json!({ "required": true, "content": content });
+    operation
+}
+
+// For endpoints that return a file instead of JSON.
+fn with_file_response(mut operation: Value) -> Value {
+    operation["responses"]["200"]["content"] = json!({
+        "application/octet-stream": { "schema": { "type": "string", "format": "binary" } },
+    });
+    operation
+}
+
+// Every operation of `filters::ledger_api`, as (method, path, operation).
+fn operations(schemas: &Map<String, Value>) -> Vec<(&'static str, &'static str, Value)> {
+    vec![
+        (
+            "post",
+            "/api/refresh",
+            operation(
+                "Reloads the ledger and the budget file",
+                vec![],
+                schema_ref("RefreshTime"),
+            ),
+        ),
+        (
+            "get",
+            "/api/balances",
+            operation(
+                "Positions of every account",
+                vec![],
+                map_of(array_of(schema_ref("Position"))),
+            ),
+        ),
+        (
+            "get",
+            "/api/journal",
+            operation(
+                "Transactions of all accounts",
+                query_params(schemas, "FilterOptions"),
+                schema_ref("Journal"),
+            ),
+        ),
+        (
+            "get",
+            "/api/account/{name}",
+            operation(
+                "Transactions of an account and its sub-accounts with running balances",
+                [
+                    vec![path_param("name")],
+                    query_params(schemas, "FilterOptions"),
+                ]
+                .concat(),
+                schema_ref("Journal"),
+            ),
+        ),
+        (
+            "get",
+            "/api/account_info/{name}",
+            operation(
+                "Overview of an account",
+                vec![path_param("name")],
+                schema_ref("AccountOverview"),
+            ),
+        ),
+        (
+            "get",
+            "/api/accounts",
+            operation(
+                "All accounts",
+                vec![],
+                array_of(schema_ref("AccountSummary")),
+            ),
+        ),
+        (
+            "get",
+            "/api/budget",
+            operation(
+                "Budget against actual spending for a period",
+                query_params(schemas, "BudgetOptions"),
+                schema_ref("BudgetReport"),
+            ),
+        ),
+        (
+            "get",
+            "/api/trie/{root}",
+            operation(
+                "Balances of the accounts under a root account",
+                [
+                    vec![path_param("root")],
+                    query_params(schemas, "TrieOptions"),
+                ]
+                .concat(),
+                schema_ref("TrieTable"),
+            ),
+        ),
+        (
+            "get",
+            "/api/changes",
+            operation(
+                "Changes found by the reloads after a generation",
+                query_params(schemas, "ChangesOptions"),
+                schema_ref("ChangeLog"),
+            ),
+        ),
+        (
+            "get",
+            "/api/complete",
+            operation(
+                "Suggestions ranked by how well and how often they match",
+                query_params(schemas, "CompleteOptions"),
+                array_of(schema_ref("Completion")),
+            ),
+        ),
+        (
+            "get",
+            "/api/errors",
+            operation(
+                "Errors found while loading the ledger",
+                vec![],
+                array_of(schema_ref("Error")),
+            ),
+        ),
+        (
+            "get",
+            "/api/commodities",
+            operation(
+                "Currencies and securities with their units held and latest price",
+                vec![],
+                array_of(schema_ref("Commodity")),
+            ),
+        ),
+        (
+            "get",
+            "/api/commodity/{commodity}",
+            operation(
+                "Price history of a commodity and the accounts holding it",
+                vec![path_param("commodity")],
+                schema_ref("CommodityDetails"),
+            ),
+        ),
+        (
+            "get",
+            "/api/documents",
+            operation(
+                "Documents of the ledger, optionally of an account and its sub-accounts",
+                query_params(schemas, "DocumentOptions"),
+                array_of(schema_ref("Document")),
+            ),
+        ),
+        (
+            "get",
+            "/api/documents/file",
+            with_file_response(operation(
+                "Contents of a document inside the document roots",
+                query_params(schemas, "DocumentFileOptions"),
+                json!({}),
+            )),
+        ),
+        (
+            "post",
+            "/api/documents",
+            with_file_body(
+                operation(
+                    "Stores a PDF or image in the first document root and adds a document entry for it",
+                    query_params(schemas, "UploadOptions"),
+                    schema_ref("UploadedDocument"),
+                ),
+                &["application/pdf", "image/png", "image/jpeg", "image/gif"],
+                "%PDF-1.4",
+            ),
+        ),
+        (
+            "get",
+            "/api/views",
+            operation("Saved views", vec![], array_of(schema_ref("View"))),
+        ),
+        (
+            "post",
+            "/api/views",
+            with_body(
+                operation(
+                    "Saves a view, replacing any view with the same name",
+                    vec![],
+                    array_of(schema_ref("View")),
+                ),
+                "View",
+                json!({ "name": "Pending", "path": "/journal", "query": "flags=!" }),
+            ),
+        ),
+        (
+            "delete",
+            "/api/views",
+            operation(
+                "Deletes a saved view",
+                query_params(schemas, "ViewOptions"),
+                array_of(schema_ref("View")),
+            ),
+        ),
+        (
+            "get",
+            "/api/options",
+            operation("Server options", vec![], schema_ref("ServerOptions")),
+        ),
+        (
+            "get",
+            "/api/openapi.json",
+            operation("This document", vec![], json!({ "type": "object" })),
+        ),
+    ]
+}
+
+pub fn document() -> Value {
+    let schemas = schemas();
+    let mut paths = Map::new();
+    for (method, path, operation) in operations(&schemas) {
+        paths
+            .entry(path)
+            .or_insert_with(|| json!({}))
+            .as_object_mut()
+            .unwrap()
+            .insert(method.to_string(), operation);
+    }
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "lumi-server",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "paths": paths,
+        "components": { "schemas": schemas },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::documents::Documents;
+    use crate::filters;
+    use crate::metrics::Metrics;
+    use crate::snapshot::Store;
+    use crate::views::Views;
+    use chrono::NaiveDate;
+    use lumi::Ledger;
+    use lumi_server_defs::*;
+    use rust_decimal::Decimal;
+    use std::collections::{BTreeSet, HashMap};
+    use std::sync::Arc;
+
+    const LEDGER: &str = r#"
+2021-01-01 open Assets:Cash USD
+2021-01-01 open Expenses:Food USD
+
+2021-01-02 * "Shop" "Lunch"
+  Assets:Cash -10 USD
+  Expenses:Food 10 USD
+
+2021-01-02 document Assets:Cash "receipt.pdf"
+"#;
+
+    fn spec_paths() -> Vec<(String, String)> {
+        let document = document();
+        let mut result = Vec::new();
+        for (path, item) in document["paths"].as_object().unwrap() {
+            for method in item.as_object().unwrap().keys() {
+                result.push((method.clone(), path.clone()));
+            }
+        }
+        result
+    }
+
+    #[tokio::test]
+    async fn every_documented_operation_is_routed() {
+        // The ledger gets its own directory, which is also the document root.
+        let dir =
+            std::env::temp_dir().join(format!("lumi-server-openapi-{}-routed", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("receipt.pdf"), b"%PDF-1.4").unwrap();
+        let path = dir.join("main.beancount");
+        std::fs::write(&path, LEDGER).unwrap();
+        let path = path.to_str().unwrap();
+        let metrics = Arc::new(Metrics::new());
+        let store = Arc::new(Store::open(path, None, metrics.clone()).unwrap());
+        let views = Arc::new(Views::open(path));
+        let documents = Arc::new(Documents::new(path, &[]));
+        let api = filters::ledger_api(store, views, documents, metrics, ServerOptions::default());
+        // In declaration order, so that a view is saved before it is deleted.
+        for (method, route, operation) in operations(&schemas()) {
+            let required: Vec<String> = operation["parameters"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter(|param| param["in"] == "query" && param["required"] == true)
+                .map(|param| {
+                    format!(
+                        "{}={}",
+                        param["name"].as_str().unwrap(),
+                        param["schema"]["example"].as_str().unwrap()
+                    )
+                })
+                .collect();
+            let uri = format!(
+                "{}?{}",
+                route
+                    .replace("{name}", "Assets:Cash")
+                    .replace("{root}", "Assets")
+                    .replace("{commodity}", "USD"),
+                required.join("&")
+            );
+            let mut request = warp::test::request()
+                .method(&method.to_uppercase())
+                .path(&uri);
+            // The first content type of the body, with its example.
+            if let Some((content_type, media)) = operation["requestBody"]["content"]
+                .as_object()
+                .and_then(|content| content.iter().next())
+            {
+                request = match media["example"].as_str() {
+                    Some(example) if content_type != "application/json" => {
+                        request.header("content-type", content_type).body(example)
+                    }
+                    _ => request.json(&media["example"]),
+                };
+            }
+            let resp = request.reply(&api).await;
+            assert_eq!(resp.status(), 200, "{} {} is not routed", method, route);
+        }
+    }
+
+    #[test]
+    fn every_route_is_documented() {
+        // Path parameters are written differently in both.
+        let normalize = |path: &str| {
+            path.split('/')
+                .map(|s| {
+                    if s.starts_with(':') || s.starts_with('{') {
+                        "*"
+                    } else {
+                        s
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("/")
+        };
+        let documented: BTreeSet<(String, String)> = spec_paths()
+            .into_iter()
+            .map(|(method, path)| (method, normalize(&path)))
+            .collect();
+        let routed: BTreeSet<(String, String)> = filters::API_ROUTES
+            .iter()
+            .map(|(method, path)| (method.to_string(), normalize(path)))
+            .collect();
+        assert_eq!(routed, documented);
+    }
+
+    const SAMPLE_LEDGER: &str = r#"
+2021-01-01 open Assets:Cash USD
+2021-01-01 open Assets:Stock
+
+2021-01-02 * "Broker" "Buy"
+  Assets:Stock 1 AAPL {10 USD} @ 10 USD
+  Assets:Cash -10 USD
+
+2021-01-03 balance Assets:Cash 5 USD
+"#;
+
+    fn keys(value: &Value) -> BTreeSet<String> {
+        value.as_object().unwrap().keys().cloned().collect()
+    }
+
+    // One serialized value of every object in the schemas. The types of lumi
+    // are taken from a parsed transaction and its errors.
+    fn samples() -> Vec<(&'static str, Value)> {
+        let dir = std::env::temp_dir().join(format!(
+            "lumi-server-openapi-{}-samples",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("main.beancount");
+        std::fs::write(&path, SAMPLE_LEDGER).unwrap();
+        let (ledger, errors) = Ledger::from_file(path.to_str().unwrap());
+        let txn = serde_json::to_value(ledger.txns().first().unwrap()).unwrap();
+        let posting = txn["postings"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|posting| !posting["cost"].is_null())
+            .unwrap()
+            .clone();
+        let date = NaiveDate::from_ymd_opt(2021, 1, 2).unwrap();
+        let number = Decimal::new(1234, 2);
+        let numbers: HashMap<String, Decimal> = [("USD".to_string(), number)].into();
+        let price = Price {
+            date,
+            number,
+            currency: "USD".to_string(),
+        };
+        let commodity = Commodity {
+            name: "AAPL".to_string(),
+            meta: vec![("name".to_string(), "Apple".to_string())],
+            precision: 2,
+            units: number,
+            latest_price: Some(price.clone()),
+        };
+        let document = Document {
+            date,
+            path: "receipt.pdf".to_string(),
+            accounts: vec!["Assets:Cash".to_string()],
+            description: Some("Receipt".to_string()),
+        };
+        let refresh = RefreshTime {
+            timestamp: 1,
+            generation: 1,
+        };
+        let balance_change = BalanceChange {
+            account: "Assets:Cash".to_string(),
+            before: numbers.clone(),
+            after: numbers.clone(),
+        };
+        let modified = ModifiedTxn {
+            before: txn.clone(),
+            after: txn.clone(),
+        };
+        let changes = Changes {
+            generation: 2,
+            loaded_at: 1,
+            added: vec![txn.clone()],
+            removed: vec![txn.clone()],
+            modified: vec![modified.clone()],
+            accounts: vec![balance_change.clone()],
+        };
+        let budget_row = BudgetRow {
+            account: "Expenses:Food".to_string(),
+            currency: "USD".to_string(),
+            budgeted: number,
+            actual: number,
+            remaining: Decimal::ZERO,
+            percent_used: Some(Decimal::new(100, 0)),
+        };
+        let trie_row = TrieTableRow {
+            level: 0,
+            name: "Assets".to_string(),
+            numbers: vec!["12.34".to_string()],
+        };
+        let account_balance = AccountBalance {
+            name: "Assets:Cash".to_string(),
+            balance: numbers.clone(),
+        };
+        let balance_assertion = BalanceAssertion {
+            date,
+            amounts: vec![],
+        };
+        let holding = CommodityHolding {
+            account: "Assets:Stock".to_string(),
+            units: number,
+            book_value: numbers.clone(),
+            market_value: numbers.clone(),
+        };
+        let samples = vec![
+            ("Amount", posting["amount"].clone()),
+            ("UnitCost", posting["cost"].clone()),
+            ("Location", txn["src"]["start"].clone()),
+            ("Source", txn["src"].clone()),
+            (
+                "Error",
+                serde_json::to_value(errors.first().unwrap()).unwrap(),
+            ),
+            ("Posting", posting.clone()),
+            ("Transaction", txn.clone()),
+            (
+                "Position",
+                json!(Position {
+                    currency: serde_json::from_value(posting["amount"]["currency"].clone())
+                        .unwrap(),
+                    number,
+                    cost: None,
+                }),
+            ),
+            (
+                "FilterOptions",
+                json!(FilterOptions {
+                    entries: Some(50),
+                    page: Some(1),
+                    old_first: Some(true),
+                    account: Some("Assets".to_string()),
+                    time: Some("2021".to_string()),
+                    show_lots: Some(true),
+                    changed_since: Some(1),
+                    flags: Some("!".to_string()),
+                    exclude_flags: Some("pad".to_string()),
+                    currency: Some("USD".to_string()),
+                    min_amount: Some(number),
+                    max_amount: Some(number),
+                    account_postings: Some(true),
+                }),
+            ),
+            (
+                "TrieOptions",
+                json!(TrieOptions {
+                    show_closed: Some(true)
+                }),
+            ),
+            (
+                "BudgetOptions",
+                json!(BudgetOptions {
+                    time: Some("2021-Q1".to_string())
+                }),
+            ),
+            ("TrieTableRow", json!(trie_row.clone())),
+            (
+                "TrieTable",
+                json!(TrieTable {
+                    rows: vec![trie_row],
+                    currencies: vec!["USD".to_string()],
+                }),
+            ),
+            (
+                "JournalItem",
+                json!(JournalItem {
+                    txn: txn.clone(),
+                    balance: numbers.clone(),
+                    changes: numbers.clone(),
+                    lots: vec![],
+                    book_value: numbers.clone(),
+                }),
+            ),
+            ("RefreshTime", json!(refresh.clone())),
+            ("AccountBalance", json!(account_balance.clone())),
+            ("BalanceAssertion", json!(balance_assertion.clone())),
+            (
+                "AccountOverview",
+                json!(AccountOverview {
+                    name: "Assets:Cash".to_string(),
+                    open: Some(date),
+                    close: Some(date),
+                    currencies: vec![],
+                    meta: vec![],
+                    balance: numbers.clone(),
+                    children: vec![account_balance],
+                    last_txn: Some(date),
+                    last_balance: Some(balance_assertion),
+                }),
+            ),
+            (
+                "AccountSummary",
+                json!(AccountSummary {
+                    name: "Assets:Cash".to_string(),
+                    open: date,
+                    close: Some(date),
+                    postings: 1,
+                }),
+            ),
+            ("BudgetRow", json!(budget_row.clone())),
+            (
+                "BudgetReport",
+                json!(BudgetReport {
+                    period: "2021-01".to_string(),
+                    start: date,
+                    end: date,
+                    previous: "2020-12".to_string(),
+                    next: "2021-02".to_string(),
+                    rows: vec![budget_row],
+                }),
+            ),
+            ("ChangesOptions", json!(ChangesOptions { since: Some(1) })),
+            ("ModifiedTxn", json!(modified)),
+            ("BalanceChange", json!(balance_change)),
+            ("Changes", json!(changes.clone())),
+            (
+                "ChangeLog",
+                json!(ChangeLog {
+                    generation: 2,
+                    truncated: false,
+                    changes: vec![changes],
+                }),
+            ),
+            (
+                "CompleteOptions",
+                json!(CompleteOptions {
+                    kind: CompletionKind::Account,
+                    prefix: "Ass".to_string(),
+                    limit: Some(10),
+                }),
+            ),
+            (
+                "Completion",
+                json!(Completion {
+                    value: "Assets:Cash".to_string(),
+                    count: 1,
+                    accounts: vec![],
+                }),
+            ),
+            (
+                "ApiError",
+                json!(ApiError {
+                    code: 400,
+                    message: "Bad request".to_string(),
+                }),
+            ),
+            (
+                "View",
+                json!(View {
+                    name: "Pending".to_string(),
+                    path: "/journal".to_string(),
+                    query: "flags=!".to_string(),
+                }),
+            ),
+            (
+                "ViewOptions",
+                json!(ViewOptions {
+                    name: "Pending".to_string(),
+                }),
+            ),
+            ("Document", json!(document.clone())),
+            (
+                "DocumentOptions",
+                json!(DocumentOptions {
+                    account: Some("Assets:Cash".to_string()),
+                }),
+            ),
+            (
+                "DocumentFileOptions",
+                json!(DocumentFileOptions {
+                    path: "receipt.pdf".to_string(),
+                }),
+            ),
+            (
+                "UploadOptions",
+                json!(UploadOptions {
+                    account: "Assets:Cash".to_string(),
+                    date,
+                    filename: "receipt.pdf".to_string(),
+                    src_file: Some("main.beancount".to_string()),
+                    src_line: Some(5),
+                }),
+            ),
+            (
+                "UploadedDocument",
+                json!(UploadedDocument { document, refresh }),
+            ),
+            ("Price", json!(price.clone())),
+            ("Commodity", json!(commodity.clone())),
+            ("CommodityHolding", json!(holding.clone())),
+            (
+                "CommodityDetails",
+                json!(CommodityDetails {
+                    commodity,
+                    prices: vec![price],
+                    holdings: vec![holding],
+                }),
+            ),
+            ("ServerOptions", json!(ServerOptions { read_only: true })),
+        ];
+        let _ = std::fs::remove_dir_all(&dir);
+        samples
+    }
+
+    #[test]
+    fn schemas_match_serialized_types() {
+        let schemas = schemas();
+        let samples = samples();
+        for (name, schema) in schemas.iter() {
+            if schema["type"] == "object" && schema.get("properties").is_some() {
+                assert!(
+                    samples.iter().any(|(sample, _)| sample == name),
+                    "{} has no sample",
+                    name
+                );
+            }
+        }
+        for (name, value) in samples {
+            let schema = &schemas[name];
+            let properties = keys(&schema["properties"]);
+            assert_eq!(
+                keys(&value),
+                properties,
+                "{} does not match its schema",
+                name
+            );
+            for required in schema["required"].as_array().unwrap() {
+                assert!(
+                    !value[required.as_str().unwrap()].is_null(),
+                    "{}.{} is required but null",
+                    name,
+                    required
+                );
+            }
+        }
+    }
+}
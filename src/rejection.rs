@@ -0,0 +1,65 @@
+use lumi_server_defs::ApiError;
+use std::convert::Infallible;
+use warp::http::StatusCode;
+use warp::reject::Reject;
+use warp::{Rejection, Reply};
+
+#[derive(Debug)]
+pub enum Failure {
+    BadRequest(String),
+    Forbidden(String),
+    NotFound(String),
+    Internal(String),
+}
+
+impl Reject for Failure {}
+
+pub fn bad_request(message: String) -> Rejection {
+    warp::reject::custom(Failure::BadRequest(message))
+}
+
+pub fn forbidden(message: String) -> Rejection {
+    warp::reject::custom(Failure::Forbidden(message))
+}
+
+pub fn not_found(message: String) -> Rejection {
+    warp::reject::custom(Failure::NotFound(message))
+}
+
+pub fn internal(message: String) -> Rejection {
+    warp::reject::custom(Failure::Internal(message))
+}
+
+pub async fn recover(rejection: Rejection) -> Result<impl Reply, Infallible> {
+    let (status, message) = if let Some(failure) = rejection.find::<Failure>() {
+        match failure {
+            Failure::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
+            Failure::Forbidden(msg) => (StatusCode::FORBIDDEN, msg.clone()),
+            Failure::NotFound(msg) => (StatusCode::NOT_FOUND, msg.clone()),
+            Failure::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.clone()),
+        }
+    } else if let Some(e) = rejection.find::<warp::reject::InvalidQuery>() {
+        (StatusCode::BAD_REQUEST, e.to_string())
+    } else if let Some(e) = rejection.find::<warp::filters::body::BodyDeserializeError>() {
+        (StatusCode::BAD_REQUEST, e.to_string())
+    } else if let Some(e) = rejection.find::<warp::reject::PayloadTooLarge>() {
+        (StatusCode::PAYLOAD_TOO_LARGE, e.to_string())
+    } else if let Some(e) = rejection.find::<warp::reject::LengthRequired>() {
+        (StatusCode::LENGTH_REQUIRED, e.to_string())
+    } else if rejection.is_not_found() {
+        (StatusCode::NOT_FOUND, "Not found".to_string())
+    } else if let Some(e) = rejection.find::<warp::reject::MethodNotAllowed>() {
+        (StatusCode::METHOD_NOT_ALLOWED, e.to_string())
+    } else {
+        log::error!("Unhandled rejection: {:?}", rejection);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Internal server error".to_string(),
+        )
+    };
+    let error = ApiError {
+        code: status.as_u16(),
+        message,
+    };
+    Ok(warp::reply::with_status(warp::reply::json(&error), status))
+}
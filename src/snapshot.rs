@@ -0,0 +1,145 @@
+use crate::budget::{self, Budget};
+use crate::changes;
+use crate::commodities::Commodities;
+use crate::directives::{self, Directive};
+use crate::metrics::Metrics;
+use lumi::{Error, Ledger, Transaction};
+use lumi_server_defs::Changes;
+use std::io;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use tokio::sync::{Mutex, MutexGuard};
+
+pub struct Snapshot {
+    pub ledger: Ledger,
+    pub errors: Vec<Error>,
+    pub budgets: Vec<Budget>,
+    pub directives: Vec<Directive>,
+    pub commodities: Commodities,
+    pub generation: u64,
+    pub loaded_at: i64,
+    pub history: Vec<Arc<Changes<Transaction>>>,
+}
+
+const MAX_HISTORY: usize = 32;
+
+pub struct Store {
+    current: RwLock<Arc<Snapshot>>,
+    reloading: Mutex<()>,
+    writing: Mutex<()>,
+    metrics: Arc<Metrics>,
+    path: String,
+    budget_path: Option<String>,
+}
+
+fn load(
+    path: &str,
+    budget_path: Option<&str>,
+    metrics: &Metrics,
+    generation: u64,
+) -> io::Result<Snapshot> {
+    // lumi reports a missing file as a ledger error, so check it first to
+    // keep the previous snapshot instead of replacing it with an empty one.
+    std::fs::File::open(path)?;
+    let start = Instant::now();
+    let (ledger, errors) = Ledger::from_file(path);
+    metrics.record_load(&ledger, &errors, start.elapsed());
+    let directives = directives::scan(path);
+    let budgets = budget::load_budgets(&directives, budget_path);
+    let commodities = Commodities::load(&ledger, &directives);
+    Ok(Snapshot {
+        ledger,
+        errors,
+        budgets,
+        directives,
+        commodities,
+        generation,
+        loaded_at: chrono::Utc::now().timestamp(),
+        history: Vec::new(),
+    })
+}
+
+impl Store {
+    pub fn open(path: &str, budget_path: Option<&str>, metrics: Arc<Metrics>) -> io::Result<Store> {
+        let snapshot = load(path, budget_path, &metrics, 1)?;
+        Ok(Store {
+            current: RwLock::new(Arc::new(snapshot)),
+            reloading: Mutex::new(()),
+            writing: Mutex::new(()),
+            metrics,
+            path: path.to_string(),
+            budget_path: budget_path.map(str::to_string),
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn snapshot(&self) -> Arc<Snapshot> {
+        self.current.read().unwrap().clone()
+    }
+
+    // Edits of the ledger files hold this until the reload that picks them
+    // up, so that they neither overwrite each other nor edit lines that an
+    // earlier edit has moved.
+    pub async fn lock_writes(&self) -> MutexGuard<'_, ()> {
+        self.writing.lock().await
+    }
+
+    // Requests that arrive while a reload is running wait for it and share
+    // its result instead of parsing the file again.
+    pub async fn reload(self: Arc<Self>) -> io::Result<Arc<Snapshot>> {
+        let seen = self.snapshot().generation;
+        let _guard = self.reloading.lock().await;
+        let current = self.snapshot();
+        if current.generation != seen {
+            return Ok(current);
+        }
+        self.parse(current).await
+    }
+
+    // A reload that is already running may have read the files before an
+    // edit, so whoever edits them has to wait for a parse that starts after.
+    pub async fn reload_after_write(self: Arc<Self>) -> io::Result<Arc<Snapshot>> {
+        let _guard = self.reloading.lock().await;
+        let current = self.snapshot();
+        self.parse(current).await
+    }
+
+    async fn parse(self: &Arc<Self>, current: Arc<Snapshot>) -> io::Result<Arc<Snapshot>> {
+        let store = self.clone();
+        let generation = current.generation + 1;
+        let result = tokio::task::spawn_blocking(move || {
+            let mut snapshot = load(
+                &store.path,
+                store.budget_path.as_deref(),
+                &store.metrics,
+                generation,
+            )?;
+            let diff = changes::diff(
+                &current.ledger,
+                &snapshot.ledger,
+                generation,
+                snapshot.loaded_at,
+            );
+            let skip = (current.history.len() + 1).saturating_sub(MAX_HISTORY);
+            snapshot.history = current.history[skip..].to_vec();
+            snapshot.history.push(Arc::new(diff));
+            Ok::<_, io::Error>(snapshot)
+        })
+        .await
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+        .and_then(|result| result);
+        // The previous snapshot stays, but health checks should see the failure.
+        let snapshot = match result {
+            Ok(snapshot) => Arc::new(snapshot),
+            Err(e) => {
+                self.metrics.record_failed_load();
+                return Err(e);
+            }
+        };
+        *self.current.write().unwrap() = snapshot.clone();
+        Ok(snapshot)
+    }
+}
@@ -0,0 +1,122 @@
+use lumi_server_defs::View;
+use std::io;
+use std::path::{Path, PathBuf};
+use tokio::sync::Mutex;
+
+pub struct Views {
+    path: PathBuf,
+    views: Mutex<Vec<View>>,
+}
+
+// `main.beancount` keeps its views in `main.beancount.views.json`.
+pub fn views_path(ledger_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.views.json", ledger_path))
+}
+
+pub fn load(path: &Path) -> Vec<View> {
+    let content = match std::fs::read(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Vec::new(),
+        Err(e) => {
+            log::error!("Cannot read {}: {}", path.display(), e);
+            return Vec::new();
+        }
+    };
+    serde_json::from_slice(&content).unwrap_or_else(|e| {
+        log::error!("Cannot parse {}: {}", path.display(), e);
+        Vec::new()
+    })
+}
+
+impl Views {
+    pub fn open(ledger_path: &str) -> Views {
+        let path = views_path(ledger_path);
+        let views = load(&path);
+        Views {
+            path,
+            views: Mutex::new(views),
+        }
+    }
+
+    pub async fn list(&self) -> Vec<View> {
+        self.views.lock().await.clone()
+    }
+
+    // Replaces the view with the same name, or appends a new one.
+    pub async fn save(&self, view: View) -> io::Result<Vec<View>> {
+        let mut views = self.views.lock().await;
+        let mut updated = views.clone();
+        match updated.iter_mut().find(|v| v.name == view.name) {
+            Some(existing) => *existing = view,
+            None => updated.push(view),
+        }
+        self.write(&updated).await?;
+        *views = updated.clone();
+        Ok(updated)
+    }
+
+    // Returns `None` if there is no view with this name.
+    pub async fn delete(&self, name: &str) -> io::Result<Option<Vec<View>>> {
+        let mut views = self.views.lock().await;
+        if !views.iter().any(|v| v.name == name) {
+            return Ok(None);
+        }
+        let updated: Vec<View> = views.iter().filter(|v| v.name != name).cloned().collect();
+        self.write(&updated).await?;
+        *views = updated.clone();
+        Ok(Some(updated))
+    }
+
+    // Writes a temporary file first so that a failed write cannot leave a
+    // truncated file behind.
+    async fn write(&self, views: &[View]) -> io::Result<()> {
+        let content = serde_json::to_vec_pretty(views)?;
+        let tmp = self.path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, content).await?;
+        tokio::fs::rename(&tmp, &self.path).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view(name: &str, query: &str) -> View {
+        View {
+            name: name.to_string(),
+            path: "/journal".to_string(),
+            query: query.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn saves_and_deletes_views_on_disk() {
+        let dir = std::env::temp_dir().join(format!("lumi-server-views-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let ledger = dir.join("main.beancount");
+        let ledger = ledger.to_str().unwrap();
+        let views = Views::open(ledger);
+        assert!(views.list().await.is_empty());
+
+        views.save(view("Pending", "flags=!")).await.unwrap();
+        views.save(view("USD", "currency=USD")).await.unwrap();
+        // the same name replaces the view in place
+        let saved = views.save(view("Pending", "flags=!&page=2")).await.unwrap();
+        assert_eq!(
+            saved,
+            vec![
+                view("Pending", "flags=!&page=2"),
+                view("USD", "currency=USD")
+            ]
+        );
+        assert_eq!(load(&views_path(ledger)), saved);
+        assert!(!views_path(ledger).with_extension("json.tmp").exists());
+
+        assert_eq!(views.delete("Unknown").await.unwrap(), None);
+        let left = views.delete("Pending").await.unwrap().unwrap();
+        assert_eq!(left, vec![view("USD", "currency=USD")]);
+        assert_eq!(Views::open(ledger).list().await, left);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}